@@ -1,19 +1,113 @@
-use macroquad::prelude::*;
-use serde::Deserialize;
-use std::fs;
-use std::collections::HashMap;
+use notan::draw::*;
+use notan::math::{Mat3, Rect, Vec2};
+use notan::prelude::*;
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AsepriteError {
+    Json(serde_json::Error),
+    Texture(String),
+    NoFrames,
+    /// A frame lasts no time at all, the index of the first one
+    NoDuration(usize),
+    /// A tag runs past the last frame or ends before it starts
+    TagOutOfRange(String),
+    UnknownTag(String),
+}
+
+impl fmt::Display for AsepriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsepriteError::Json(e) => write!(f, "invalid sprite sheet json: {}", e),
+            AsepriteError::Texture(e) => write!(f, "couldn't create the sheet's texture: {}", e),
+            AsepriteError::NoFrames => write!(f, "sprite sheet has no frames"),
+            AsepriteError::NoDuration(frame) => write!(f, "frame {} has no duration", frame),
+            AsepriteError::TagOutOfRange(name) => write!(f, "tag '{}' is out of range", name),
+            AsepriteError::UnknownTag(name) => write!(f, "no tag named '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for AsepriteError {}
 
 #[derive(Debug, Deserialize)]
 struct AsepriteData {
-    frames: HashMap<String, FrameData>,
+    frames: Frames,
     meta: MetaData,
 }
 
+/// Frames in the order they appear in the export. Aseprite writes them either as an object
+/// keyed by filename ("Hash") or as an array ("Array"), both in frame order.
+#[derive(Debug)]
+struct Frames(Vec<FrameData>);
+
+impl<'de> Deserialize<'de> for Frames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = Frames;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a map or array of frames")
+            }
+
+            // The keys are filenames, which follow whatever template the export used, so
+            // only their order is kept
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Frames, A::Error> {
+                let mut frames = Vec::new();
+                while let Some((_, frame)) = map.next_entry::<String, FrameData>()? {
+                    frames.push(frame);
+                }
+                Ok(Frames(frames))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Frames, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(frame) = seq.next_element()? {
+                    frames.push(frame);
+                }
+                Ok(Frames(frames))
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MetaData {
+    #[serde(rename = "frameTags", default)]
+    frame_tags: Vec<Tag>,
+    #[serde(default)]
+    slices: Vec<SliceData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SliceData {
+    name: String,
+    keys: Vec<SliceKeyData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SliceKeyData {
+    frame: usize,
+    bounds: AsepriteRect,
+    center: Option<AsepriteRect>,
+    pivot: Option<Point>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
 #[derive(Debug, Deserialize)]
 struct FrameData {
     frame: AsepriteRect,
-    rotated: bool,
-    trimmed: bool,
     #[serde(rename = "spriteSourceSize")]
     sprite_source_size: AsepriteRect,
     #[serde(rename = "sourceSize")]
@@ -29,116 +123,694 @@ struct AsepriteRect {
     h: i32,
 }
 
-#[derive(Debug, Deserialize)]
-struct MetaData {
-    app: String,
-    version: String,
-    image: String,
-    format: String,
-    size: Size,
-    scale: String,
-    #[serde(rename = "frameTags")]
-    frame_tags: Vec<FrameTag>,
-    layers: Vec<Layer>,
-    slices: Vec<String>, // This is empty in the provided JSON, so we'll use Vec<String> for simplicity
-}
-
 #[derive(Debug, Deserialize)]
 struct Size {
     w: i32,
     h: i32,
 }
 
-#[derive(Debug, Deserialize)]
-struct FrameTag {
-    name: String,
-    from: i32,
-    to: i32,
-    direction: String,
-    color: String,
+/// Which way a tag plays through its frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Forward,
+    Reverse,
+    /// There and back again, without repeating the frames at either end
+    #[serde(rename = "pingpong")]
+    PingPong,
+    #[serde(rename = "pingpong_reverse")]
+    PingPongReverse,
 }
 
-#[derive(Debug, Deserialize)]
-struct Layer {
-    name: String,
-    opacity: i32,
-    #[serde(rename = "blendMode")]
-    blend_mode: String,
+/// A named range of frames, inclusive at both ends.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: Direction,
+}
+
+/// A named rectangle drawn over the sprite in Aseprite, used for hurtboxes, attachment points
+/// and the sprite's origin. It can move from frame to frame.
+#[derive(Clone, Debug)]
+pub struct Slice {
+    pub name: String,
+    /// Sorted by frame, each key holds until the next one
+    pub keys: Vec<SliceKey>,
+}
+
+/// Where a slice is from `frame` on, in pixels from the top-left of the untrimmed frame.
+#[derive(Clone, Copy, Debug)]
+pub struct SliceKey {
+    pub frame: usize,
+    pub bounds: Rect,
+    /// The part of a 9-slice that stretches, relative to `bounds`
+    pub center: Option<Rect>,
+    /// Relative to `bounds`
+    pub pivot: Option<Vec2>,
+}
+
+impl Slice {
+    pub fn key_at(&self, frame: usize) -> Option<&SliceKey> {
+        self.keys.iter().rev().find(|key| key.frame <= frame)
+    }
+}
+
+impl From<SliceData> for Slice {
+    fn from(slice: SliceData) -> Self {
+        let mut keys: Vec<SliceKey> = slice
+            .keys
+            .iter()
+            .map(|key| SliceKey {
+                frame: key.frame,
+                bounds: (&key.bounds).into(),
+                center: key.center.as_ref().map(Rect::from),
+                pivot: key
+                    .pivot
+                    .as_ref()
+                    .map(|pivot| Vec2::new(pivot.x as f32, pivot.y as f32)),
+            })
+            .collect();
+        keys.sort_by_key(|key| key.frame);
+        Slice {
+            name: slice.name,
+            keys,
+        }
+    }
+}
+
+impl From<&AsepriteRect> for Rect {
+    fn from(rect: &AsepriteRect) -> Self {
+        Rect {
+            x: rect.x as f32,
+            y: rect.y as f32,
+            width: rect.w as f32,
+            height: rect.h as f32,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Where the frame is in the sheet's image, position then size
+    pub source: (Vec2, Vec2),
+    /// Where the cropped image goes inside the full-size frame, non-zero for trimmed exports
+    pub offset: Vec2,
+    /// Size of the frame before trimming
+    pub size: Vec2,
+    /// Seconds
+    pub duration: f32,
+}
+
+/// An Aseprite JSON export and its image, loaded once and shared by every animation using it.
+#[derive(Clone)]
+pub struct SpriteSheet {
+    pub texture: Texture,
+    pub frames: Vec<Frame>,
+    pub tags: Vec<Tag>,
+    pub slices: Vec<Slice>,
+}
+
+impl SpriteSheet {
+    /// Loads a sheet exported from Aseprite as JSON, with frames as either a hash or an array.
+    pub fn from_aseprite(
+        gfx: &mut Graphics,
+        json: &str,
+        image: &[u8],
+    ) -> Result<Self, AsepriteError> {
+        let data: AsepriteData = serde_json::from_str(json).map_err(AsepriteError::Json)?;
+        if data.frames.0.is_empty() {
+            return Err(AsepriteError::NoFrames);
+        }
+        if let Some(i) = data.frames.0.iter().position(|frame| frame.duration <= 0) {
+            return Err(AsepriteError::NoDuration(i));
+        }
+
+        let texture = gfx
+            .create_texture()
+            .from_image(image)
+            .with_filter(TextureFilter::Nearest, TextureFilter::Nearest)
+            .build()
+            .map_err(AsepriteError::Texture)?;
+
+        let frames: Vec<Frame> = data
+            .frames
+            .0
+            .into_iter()
+            .map(|frame| Frame {
+                source: (
+                    Vec2::new(frame.frame.x as f32, frame.frame.y as f32),
+                    Vec2::new(frame.frame.w as f32, frame.frame.h as f32),
+                ),
+                offset: Vec2::new(
+                    frame.sprite_source_size.x as f32,
+                    frame.sprite_source_size.y as f32,
+                ),
+                size: Vec2::new(frame.source_size.w as f32, frame.source_size.h as f32),
+                duration: frame.duration as f32 / 1000.0,
+            })
+            .collect();
+
+        let slices = data.meta.slices.into_iter().map(Slice::from).collect();
+
+        let sheet = SpriteSheet {
+            texture,
+            frames,
+            tags: data.meta.frame_tags,
+            slices,
+        };
+        check_tags(&sheet.tags, sheet.frames.len())?;
+        Ok(sheet)
+    }
+
+    /// Adds tags and slices from JSON laid out like the export's `meta`, for ones the game needs
+    /// that the .ase file doesn't have. They replace any exported ones with the same name.
+    pub fn extend(&mut self, json: &str) -> Result<(), AsepriteError> {
+        let meta: MetaData = serde_json::from_str(json).map_err(AsepriteError::Json)?;
+        check_tags(&meta.frame_tags, self.frames.len())?;
+        for tag in meta.frame_tags {
+            self.tags.retain(|existing| existing.name != tag.name);
+            self.tags.push(tag);
+        }
+        for slice in meta.slices {
+            self.slices.retain(|existing| existing.name != slice.name);
+            self.slices.push(slice.into());
+        }
+        Ok(())
+    }
+}
+
+fn check_tags(tags: &[Tag], frame_count: usize) -> Result<(), AsepriteError> {
+    match tags
+        .iter()
+        .find(|tag| tag.from > tag.to || tag.to >= frame_count)
+    {
+        Some(tag) => Err(AsepriteError::TagOutOfRange(tag.name.clone())),
+        None => Ok(()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A one-shot tag reached its last frame and stopped there
+    Finished(String),
 }
 
-struct Animation {
-    texture: Texture2D,
-    frames: Vec<FrameData>,
+/// Plays a sheet's tags, one at a time. Without a tag it loops through every frame.
+pub struct Animation {
+    sheet: SpriteSheet,
+    tag: Tag,
+    once: bool,
+    finished: bool,
     current_frame: usize,
     frame_time: f32,
+    // +1 or -1, flips at the ends of a ping-pong
+    step: isize,
+    // Whether a ping-pong is on its way back
+    returning: bool,
+    /// Multiplies the time passed to `update`, 2.0 plays at double speed
+    pub speed: f32,
 }
 
 impl Animation {
-    async fn new(json_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let json_data = load_string(json_path).await?;
-        let aseprite_data: AsepriteData = serde_json::from_str(&json_data)?;
-        
-        let texture = load_texture(&format!("assets/{}", aseprite_data.meta.image)).await?;
-        texture.set_filter(FilterMode::Nearest);
-
-        // Convert HashMap to Vec, sorted by frame number
-        let mut frames: Vec<_> = aseprite_data.frames.into_iter().collect();
-        frames.sort_by(|(a, _), (b, _)| {
-            let a_num: i32 = a.split_whitespace().last().unwrap().trim_end_matches(".ase").parse().unwrap();
-            let b_num: i32 = b.split_whitespace().last().unwrap().trim_end_matches(".ase").parse().unwrap();
-            a_num.cmp(&b_num)
-        });
-        let frames = frames.into_iter().map(|(_, frame_data)| frame_data).collect();
-
-        Ok(Animation {
-            texture,
-            frames,
+    pub fn new(sheet: &SpriteSheet) -> Self {
+        let whole_sheet = Tag {
+            name: String::new(),
+            from: 0,
+            to: sheet.frames.len() - 1,
+            direction: Direction::Forward,
+        };
+        Animation {
+            sheet: sheet.clone(),
+            tag: whole_sheet,
+            once: false,
+            finished: false,
             current_frame: 0,
             frame_time: 0.0,
-        })
+            step: 1,
+            returning: false,
+            speed: 1.0,
+        }
     }
 
-    fn update(&mut self, dt: f32) {
-        self.frame_time += dt;
-        if self.frame_time >= self.frames[self.current_frame].duration as f32 / 1000.0 {
-            self.current_frame = (self.current_frame + 1) % self.frames.len();
-            self.frame_time = 0.0;
+    /// Loops the named tag. Does nothing if it's already playing, so it can be called every tick.
+    pub fn play(&mut self, name: &str) -> Result<(), AsepriteError> {
+        if self.tag.name == name && !self.once {
+            return Ok(());
         }
+        let tag = self.find_tag(name)?;
+        self.start(tag, false);
+        Ok(())
     }
 
-    fn draw(&self, x: f32, y: f32) {
-        let scale = 10;
-        let frame = &self.frames[self.current_frame];
-        let source_rect = Rect::new(
-            frame.frame.x as f32,
-            frame.frame.y as f32,
-            frame.frame.w as f32,
-            frame.frame.h as f32,
+    /// Plays the named tag once and stops on its last frame, reporting `Finished` from `update`.
+    /// Restarts the tag if it's already playing.
+    pub fn play_once(&mut self, name: &str) -> Result<(), AsepriteError> {
+        let tag = self.find_tag(name)?;
+        self.start(tag, true);
+        Ok(())
+    }
+
+    fn find_tag(&self, name: &str) -> Result<Tag, AsepriteError> {
+        self.sheet
+            .tags
+            .iter()
+            .find(|tag| tag.name == name)
+            .cloned()
+            .ok_or_else(|| AsepriteError::UnknownTag(name.to_string()))
+    }
+
+    fn start(&mut self, tag: Tag, once: bool) {
+        let reversed = matches!(
+            tag.direction,
+            Direction::Reverse | Direction::PingPongReverse
         );
-        draw_texture_ex(
-            &self.texture,
-            x,
-            y,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(vec2((frame.source_size.w * scale) as f32, (frame.source_size.h * scale)as f32)),
-                source: Some(source_rect),
-                ..Default::default()
-            },
+        self.current_frame = if reversed { tag.to } else { tag.from };
+        self.step = if reversed { -1 } else { 1 };
+        self.tag = tag;
+        self.once = once;
+        self.finished = false;
+        self.returning = false;
+        self.frame_time = 0.0;
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag.name
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.sheet.frames[self.current_frame]
+    }
+
+    pub fn update(&mut self, dt: f32) -> Vec<AnimationEvent> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+
+        self.frame_time += dt * self.speed;
+        // Long frames of `dt` can skip several short frames of animation
+        while !self.finished && self.frame_time >= self.frame().duration {
+            self.frame_time -= self.frame().duration;
+            events.extend(self.advance());
+        }
+        events
+    }
+
+    fn advance(&mut self) -> Option<AnimationEvent> {
+        let next = self.current_frame as isize + self.step;
+        if next >= self.tag.from as isize && next <= self.tag.to as isize {
+            self.current_frame = next as usize;
+            return None;
+        }
+
+        // Past the end of a pass through the tag
+        let ping_pong = matches!(
+            self.tag.direction,
+            Direction::PingPong | Direction::PingPongReverse
+        );
+        if ping_pong && !self.returning {
+            self.returning = true;
+            self.step = -self.step;
+            self.step_within_tag();
+            return None;
+        }
+        if self.once {
+            self.finished = true;
+            return Some(AnimationEvent::Finished(self.tag.name.clone()));
+        }
+
+        if ping_pong {
+            self.returning = false;
+            self.step = -self.step;
+            self.step_within_tag();
+        } else {
+            self.current_frame = if self.step > 0 {
+                self.tag.from
+            } else {
+                self.tag.to
+            };
+        }
+        None
+    }
+
+    // Moves one frame in the current direction, staying put on single-frame tags
+    fn step_within_tag(&mut self) {
+        let next = self.current_frame as isize + self.step;
+        if next >= self.tag.from as isize && next <= self.tag.to as isize {
+            self.current_frame = next as usize;
+        }
+    }
+
+    /// The named slice where it is on the current frame, in frame pixels.
+    pub fn slice(&self, name: &str) -> Option<&SliceKey> {
+        self.sheet
+            .slices
+            .iter()
+            .find(|slice| slice.name == name)?
+            .key_at(self.current_frame)
+    }
+
+    /// The named slice on the current frame, in the world, for a sprite drawn at `position`.
+    pub fn slice_bounds(&self, name: &str, position: Vec2, flip_x: bool) -> Option<Rect> {
+        let bounds = self.slice(name)?.bounds;
+        Some(self.rect_to_world(bounds, position, flip_x))
+    }
+
+    /// The named slice's pivot on the current frame, in the world, for a sprite drawn at
+    /// `position`. Slices without a pivot use their center.
+    pub fn slice_pivot(&self, name: &str, position: Vec2, flip_x: bool) -> Option<Vec2> {
+        let key = self.slice(name)?;
+        Some(self.to_world(pivot_of(key), position, flip_x))
+    }
+
+    // The point of the frame that goes on the position the sprite is drawn at: the pivot of an
+    // `origin` slice, or the middle of the frame
+    fn origin(&self) -> Vec2 {
+        match self.slice("origin") {
+            Some(key) => pivot_of(key),
+            None => self.frame().size / 2.0,
+        }
+    }
+
+    /// Outlines every slice on the current frame for a sprite drawn at `position`, with the
+    /// part of a 9-slice that stretches inside it.
+    pub fn draw_slices(&self, draw: &mut Draw, position: Vec2, flip_x: bool) {
+        for slice in &self.sheet.slices {
+            let Some(key) = slice.key_at(self.current_frame) else {
+                continue;
+            };
+            let center = key.center.map(|center| Rect {
+                x: key.bounds.x + center.x,
+                y: key.bounds.y + center.y,
+                ..center
+            });
+            for rect in std::iter::once(key.bounds).chain(center) {
+                let rect = self.rect_to_world(rect, position, flip_x);
+                draw.rect((rect.x, rect.y), (rect.width, rect.height))
+                    .stroke_color(Color::YELLOW);
+            }
+        }
+    }
+
+    fn rect_to_world(&self, rect: Rect, position: Vec2, flip_x: bool) -> Rect {
+        let a = self.to_world(Vec2::new(rect.x, rect.y), position, flip_x);
+        let b = self.to_world(
+            Vec2::new(rect.x + rect.width, rect.y + rect.height),
+            position,
+            flip_x,
         );
+        let min = a.min(b);
+        Rect {
+            x: min.x,
+            y: min.y,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+
+    fn to_world(&self, point: Vec2, position: Vec2, flip_x: bool) -> Vec2 {
+        let mut offset = point - self.origin();
+        if flip_x {
+            offset.x = -offset.x;
+        }
+        position + offset
+    }
+
+    /// Draws the current frame with its origin on `position`, mirrored around it when `flip_x`
+    /// is set. The origin is the pivot of the `origin` slice, or the middle of the frame.
+    pub fn draw(&self, draw: &mut Draw, position: Vec2, flip_x: bool, opacity: f32) {
+        let frame = self.frame();
+        let top_left = position - self.origin();
+        let flip = if flip_x {
+            Mat3::from_translation(position)
+                * Mat3::from_scale(Vec2::new(-1.0, 1.0))
+                * Mat3::from_translation(-position)
+        } else {
+            Mat3::IDENTITY
+        };
+
+        let (source, size) = frame.source;
+        draw.image(&self.sheet.texture)
+            .crop(source.into(), size.into())
+            .position(top_left.x + frame.offset.x, top_left.y + frame.offset.y)
+            .alpha(opacity)
+            .transform(flip);
     }
 }
 
-#[macroquad::main("Aseprite Animation")]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut animation = Animation::new("assets/skeletron.json").await?;
+fn pivot_of(key: &SliceKey) -> Vec2 {
+    let bounds = key.bounds;
+    let corner = Vec2::new(bounds.x, bounds.y);
+    match key.pivot {
+        Some(pivot) => corner + pivot,
+        None => corner + Vec2::new(bounds.width, bounds.height) / 2.0,
+    }
+}
 
-    loop {
-        clear_background(Color::new(0.1, 0.2, 0.3, 1.0));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
 
-        animation.update(get_frame_time());
-        animation.draw(screen_width() / 2.0, screen_height() / 2.0);
+    // Three 100ms frames in a hash export, with the durations swapped out for `durations`
+    fn sheet(durations: [i32; 3]) -> Result<SpriteSheet, AsepriteError> {
+        let frames: Vec<String> = durations
+            .iter()
+            .enumerate()
+            .map(|(i, duration)| {
+                format!(
+                    r#""f{i}": {{ "frame": {{ "x": {x}, "y": 0, "w": 4, "h": 4 }},
+                        "spriteSourceSize": {{ "x": 0, "y": 0, "w": 4, "h": 4 }},
+                        "sourceSize": {{ "w": 4, "h": 4 }}, "duration": {duration} }}"#,
+                    x = i * 4
+                )
+            })
+            .collect();
+        let json = format!(
+            r#"{{ "frames": {{ {} }}, "meta": {{ "frameTags": [
+                {{ "name": "idle", "from": 0, "to": 2, "direction": "forward" }}
+            ] }} }}"#,
+            frames.join(",")
+        );
+        SpriteSheet::from_aseprite(&mut headless::graphics(), &json, &[])
+    }
+
+    // One 4x4 frame at `x` in the sheet's image
+    fn frame(x: i32) -> String {
+        format!(
+            r#"{{ "frame": {{ "x": {x}, "y": 0, "w": 4, "h": 4 }},
+                "spriteSourceSize": {{ "x": 0, "y": 0, "w": 4, "h": 4 }},
+                "sourceSize": {{ "w": 4, "h": 4 }}, "duration": 100 }}"#
+        )
+    }
+
+    fn source_xs(sheet: &SpriteSheet) -> Vec<f32> {
+        sheet.frames.iter().map(|frame| frame.source.0.x).collect()
+    }
+
+    #[test]
+    fn reads_array_exports() {
+        let json = format!(
+            r#"{{ "frames": [{}, {}], "meta": {{}} }}"#,
+            frame(0),
+            frame(4)
+        );
+        let sheet = SpriteSheet::from_aseprite(&mut headless::graphics(), &json, &[]).unwrap();
+        assert_eq!(source_xs(&sheet), [0.0, 4.0]);
+    }
+
+    #[test]
+    fn hash_exports_keep_their_order_not_the_keys() {
+        let json = format!(
+            r#"{{ "frames": {{ "idle_b": {}, "idle_a": {}, "idle_c": {} }}, "meta": {{}} }}"#,
+            frame(0),
+            frame(4),
+            frame(8)
+        );
+        let sheet = SpriteSheet::from_aseprite(&mut headless::graphics(), &json, &[]).unwrap();
+        assert_eq!(source_xs(&sheet), [0.0, 4.0, 8.0]);
+    }
+
+    #[test]
+    fn malformed_exports_are_errors() {
+        let mut gfx = headless::graphics();
+        let missing_duration = r#"{ "frames": [{ "frame": { "x": 0, "y": 0, "w": 4, "h": 4 } }],
+            "meta": {} }"#;
+        for json in [
+            "",
+            "{ \"frames\": [",
+            r#"{ "frames": 5, "meta": {} }"#,
+            missing_duration,
+        ] {
+            let result = SpriteSheet::from_aseprite(&mut gfx, json, &[]);
+            assert!(matches!(result, Err(AsepriteError::Json(_))), "{}", json);
+        }
+        let empty = SpriteSheet::from_aseprite(&mut gfx, r#"{ "frames": [], "meta": {} }"#, &[]);
+        assert!(matches!(empty, Err(AsepriteError::NoFrames)));
+    }
+
+    #[test]
+    fn rejects_frames_without_a_duration() {
+        let err = sheet([100, 0, 100]).err().unwrap();
+        assert!(matches!(err, AsepriteError::NoDuration(1)));
+        let err = sheet([100, 100, -5]).err().unwrap();
+        assert!(matches!(err, AsepriteError::NoDuration(2)));
+    }
+
+    #[test]
+    fn extend_adds_and_replaces_tags() {
+        let mut sheet = sheet([100; 3]).unwrap();
+        sheet
+            .extend(
+                r#"{ "frameTags": [
+                    { "name": "idle", "from": 0, "to": 0, "direction": "forward" },
+                    { "name": "hurt", "from": 1, "to": 2, "direction": "pingpong" }
+                ] }"#,
+            )
+            .unwrap();
+        let names: Vec<&str> = sheet.tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(names, ["idle", "hurt"]);
+        assert_eq!(sheet.tags[0].to, 0);
+
+        let out_of_range = r#"{ "frameTags": [
+            { "name": "walk", "from": 1, "to": 3, "direction": "forward" }
+        ] }"#;
+        assert!(matches!(
+            sheet.extend(out_of_range),
+            Err(AsepriteError::TagOutOfRange(name)) if name == "walk"
+        ));
+        assert_eq!(sheet.tags.len(), 2);
+    }
+
+    #[test]
+    fn unknown_tags_keep_the_current_one_playing() {
+        let sheet = sheet([100; 3]).unwrap();
+        let mut animation = Animation::new(&sheet);
+        animation.play("idle").unwrap();
+        animation.update(0.15);
+
+        assert!(animation.play("walk").is_err());
+        assert!(animation.play_once("hurt").is_err());
+        assert_eq!(animation.tag(), "idle");
+        assert_eq!(animation.current_frame, 1);
+    }
 
-        next_frame().await;
+    // Each direction as a tag over all three frames
+    fn directions() -> SpriteSheet {
+        let mut sheet = sheet([100; 3]).unwrap();
+        sheet
+            .extend(
+                r#"{ "frameTags": [
+                    { "name": "reverse", "from": 0, "to": 2, "direction": "reverse" },
+                    { "name": "pingpong", "from": 0, "to": 2, "direction": "pingpong" },
+                    { "name": "pingpong_reverse", "from": 0, "to": 2,
+                      "direction": "pingpong_reverse" }
+                ] }"#,
+            )
+            .unwrap();
+        sheet
     }
-}
\ No newline at end of file
+
+    // The frame it starts on, then the frame after each of `steps` 100ms updates
+    fn frames(animation: &mut Animation, steps: usize) -> Vec<usize> {
+        let mut frames = vec![animation.current_frame];
+        for _ in 0..steps {
+            animation.update(0.1);
+            frames.push(animation.current_frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn tags_play_in_their_direction() {
+        let sheet = directions();
+        let mut animation = Animation::new(&sheet);
+        let mut play = |name: &str| {
+            animation.play(name).unwrap();
+            frames(&mut animation, 6)
+        };
+
+        assert_eq!(play("idle"), [0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(play("reverse"), [2, 1, 0, 2, 1, 0, 2]);
+        // The frames at either end show once per turnaround
+        assert_eq!(play("pingpong"), [0, 1, 2, 1, 0, 1, 2]);
+        assert_eq!(play("pingpong_reverse"), [2, 1, 0, 1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn only_one_shots_finish_and_only_once() {
+        let sheet = directions();
+        let mut animation = Animation::new(&sheet);
+        let mut events = Vec::new();
+
+        animation.play("idle").unwrap();
+        for _ in 0..10 {
+            events.extend(animation.update(0.1));
+        }
+        assert!(events.is_empty());
+
+        animation.play_once("idle").unwrap();
+        for _ in 0..10 {
+            events.extend(animation.update(0.1));
+        }
+        assert_eq!(events, [AnimationEvent::Finished("idle".to_string())]);
+        assert_eq!(animation.current_frame, 2);
+
+        // A one-shot ping-pong finishes back where it started
+        animation.play_once("pingpong").unwrap();
+        assert_eq!(frames(&mut animation, 6), [0, 1, 2, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn speed_scales_the_time_per_frame() {
+        let sheet = sheet([100; 3]).unwrap();
+        let mut animation = Animation::new(&sheet);
+        animation.update(0.05);
+        assert_eq!(animation.current_frame, 0);
+
+        animation.speed = 2.0;
+        animation.update(0.05);
+        assert_eq!(animation.current_frame, 1);
+        animation.update(0.05);
+        assert_eq!(animation.current_frame, 2);
+    }
+
+    #[test]
+    fn slices_follow_keys_and_the_origin() {
+        let mut sheet = sheet([100; 3]).unwrap();
+        sheet
+            .extend(
+                r#"{ "slices": [
+                    { "name": "origin", "keys": [
+                        { "frame": 0, "bounds": { "x": 2, "y": 4, "w": 1, "h": 1 } }
+                    ] },
+                    { "name": "panel", "keys": [
+                        { "frame": 2, "bounds": { "x": 1, "y": 1, "w": 3, "h": 3 } },
+                        { "frame": 0, "bounds": { "x": 0, "y": 0, "w": 4, "h": 4 },
+                          "center": { "x": 1, "y": 1, "w": 2, "h": 2 },
+                          "pivot": { "x": 3, "y": 1 } }
+                    ] }
+                ] }"#,
+            )
+            .unwrap();
+        let mut animation = Animation::new(&sheet);
+        let key = animation.slice("panel").unwrap();
+        assert_eq!(key.frame, 0);
+        assert_eq!(key.center.unwrap().width, 2.0);
+
+        // The origin's center, (2.5, 4.5), goes on the position the sprite is drawn at
+        let position = Vec2::new(100.0, 100.0);
+        let pivot = animation.slice_pivot("panel", position, false).unwrap();
+        assert_eq!(pivot, Vec2::new(100.5, 96.5));
+        let flipped = animation.slice_pivot("panel", position, true).unwrap();
+        assert_eq!(flipped, Vec2::new(99.5, 96.5));
+        let bounds = animation.slice_bounds("panel", position, true).unwrap();
+        assert_eq!((bounds.x, bounds.width), (98.5, 4.0));
+
+        animation.update(0.25);
+        assert_eq!(animation.slice("panel").unwrap().frame, 2);
+        assert!(animation.slice("missing").is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::timer::Timer;
+use crate::{GAME_HEIGHT, GAME_WIDTH};
+use notan::math::Vec2;
+
+// How quickly the camera catches up with its target, per second
+const CAMERA_EASING: f32 = 1.0;
+
+/// Eases towards a target and shakes on demand. `pos` is the translation applied to the world,
+/// so a target in the middle of the screen gives a `pos` of zero.
+pub struct Camera {
+    pub pos: Vec2,
+    pub prev_pos: Vec2,
+    pub shake_offset: Vec2,
+    shake_strength: f32,
+    shake_timer: Timer,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        let mut shake_timer = Timer::new(0.1);
+        shake_timer.finish();
+
+        Camera {
+            pos: Vec2::ZERO,
+            prev_pos: Vec2::ZERO,
+            shake_offset: Vec2::ZERO,
+            shake_strength: 0.0,
+            shake_timer,
+        }
+    }
+
+    pub fn follow(&mut self, target: Vec2, dt: f32) {
+        self.prev_pos = self.pos;
+        self.pos += (centered_on(target) - self.pos) * CAMERA_EASING * dt;
+
+        self.shake_timer.update(dt);
+        // Set camera to a random position in a small range
+        let strength = if self.shake_timer.is_finished() {
+            0.0
+        } else {
+            self.shake_strength
+        };
+        self.shake_offset = Vec2::new(rand::random::<f32>(), rand::random::<f32>()) * strength
+            - Vec2::splat(strength / 2.0);
+    }
+
+    /// Jumps straight to the target, used when a scene starts so it doesn't pan in from the origin.
+    pub fn snap(&mut self, target: Vec2) {
+        self.pos = centered_on(target);
+        self.prev_pos = self.pos;
+    }
+
+    /// Shakes by up to `strength` pixels for a moment, a weaker shake never cuts a stronger one short.
+    pub fn shake(&mut self, strength: f32) {
+        if self.shake_timer.is_finished() || strength >= self.shake_strength {
+            self.shake_strength = strength;
+            self.shake_timer.reset();
+        }
+    }
+
+    /// Translation to draw with, `alpha` blends from the previous tick to the current one.
+    pub fn render_pos(&self, alpha: f32) -> Vec2 {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
+}
+
+fn centered_on(target: Vec2) -> Vec2 {
+    -target + Vec2::new(GAME_WIDTH as f32, GAME_HEIGHT as f32) / 2.0
+}
@@ -0,0 +1,138 @@
+use crate::timer::Timer;
+use notan::math::Vec2;
+
+/// Which side something fights for, nothing hurts its own side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Team {
+    Player,
+    Enemy,
+}
+
+/// The player's hurtbox owner, map objects are numbered from 1 so it can't clash with them.
+pub const PLAYER_OWNER: u32 = 0;
+
+/// The circle something can be hit in, rebuilt from its owner's position every tick.
+#[derive(Clone, Copy, Debug)]
+pub struct Hurtbox {
+    pub position: Vec2,
+    pub radius: f32,
+    pub team: Team,
+    /// Stays the same for as long as the owner is around, unlike its index in the hurtboxes
+    /// passed around each tick
+    pub owner: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Damage {
+    pub amount: f32,
+    /// Added to the velocity of whatever got hit
+    pub knockback: Vec2,
+}
+
+/// A hit on the hurtbox at `target`, an index into the hurtboxes that were checked.
+#[derive(Clone, Copy, Debug)]
+pub struct DamageEvent {
+    pub target: usize,
+    pub position: Vec2,
+    pub damage: Damage,
+}
+
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    // Runs after every hit that lands, more hits are ignored until it finishes
+    iframes: Timer,
+}
+
+impl Health {
+    pub fn new(max: f32, iframe_duration: f32) -> Self {
+        let mut iframes = Timer::new(iframe_duration);
+        iframes.finish();
+
+        Health {
+            current: max,
+            max,
+            iframes,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.iframes.update(dt);
+    }
+
+    /// Returns whether the hit landed, it doesn't while invulnerable or already dead.
+    pub fn take(&mut self, damage: &Damage) -> bool {
+        if self.is_invulnerable() || self.is_dead() {
+            return false;
+        }
+        self.current = (self.current - damage.amount).max(0.0);
+        self.iframes.reset();
+        true
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        !self.iframes.is_finished()
+    }
+
+    /// Seconds since the last hit landed, for blinking while invulnerable
+    pub fn iframe_time(&self) -> f32 {
+        self.iframes.time
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Hurtboxes not on `team` touching a circle, for explosions and anything else that hits an area.
+pub fn overlapping(
+    hurtboxes: &[Hurtbox],
+    position: Vec2,
+    radius: f32,
+    team: Team,
+) -> impl Iterator<Item = usize> + '_ {
+    hurtboxes
+        .iter()
+        .enumerate()
+        .filter(move |(_, hurtbox)| {
+            hurtbox.team != team && hurtbox.position.distance(position) <= radius + hurtbox.radius
+        })
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn damage(amount: f32) -> Damage {
+        Damage {
+            amount,
+            knockback: Vec2::ZERO,
+        }
+    }
+
+    #[test]
+    fn hits_during_iframes_are_ignored() {
+        let mut health = Health::new(5.0, 0.5);
+        assert!(health.take(&damage(1.0)));
+        assert!(health.is_invulnerable());
+
+        health.update(0.25);
+        assert!(!health.take(&damage(1.0)));
+        assert_eq!(health.current, 4.0);
+
+        health.update(0.25);
+        assert!(!health.is_invulnerable());
+        assert!(health.take(&damage(1.0)));
+        assert_eq!(health.current, 3.0);
+    }
+
+    #[test]
+    fn health_stops_at_zero() {
+        let mut health = Health::new(2.0, 0.0);
+        assert!(health.take(&damage(5.0)));
+        assert_eq!(health.current, 0.0);
+        assert!(health.is_dead());
+        assert!(!health.take(&damage(1.0)));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::combat::{Health, Hurtbox, Team};
+use crate::gun::{Gun, GunEvent, Weapon};
+use crate::pathfinding::PathOptions;
+use crate::tilemap::{MapObject, TileMap};
+use crate::timer::Timer;
+use crate::top_down;
+use notan::draw::*;
+use notan::math::Vec2;
+use notan::prelude::*;
+
+/// How far an enemy can spot the player from, in pixels
+const SIGHT_RANGE: f32 = 120.0;
+/// Ranged enemies back off when the player is closer than this and close in when further
+const PREFERRED_RANGE: f32 = 70.0;
+/// Seconds an enemy keeps chasing or fleeing after losing sight of the player
+const MEMORY: f32 = 2.0;
+
+/// What an enemy does once it spots the player, set per map object with a `behavior`
+/// property. Until then everything but `Idle` wanders around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Behavior {
+    Idle,
+    Wander,
+    Chase,
+    Flee,
+    Ranged,
+}
+
+impl Behavior {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "idle" => Ok(Behavior::Idle),
+            "wander" => Ok(Behavior::Wander),
+            "chase" => Ok(Behavior::Chase),
+            "flee" => Ok(Behavior::Flee),
+            "ranged" => Ok(Behavior::Ranged),
+            _ => Err(format!("unknown enemy behavior '{}'", name)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Idle,
+    Wander,
+    Chase,
+    Flee,
+    Attack,
+}
+
+pub struct Enemy {
+    /// The map object it came from
+    pub id: u32,
+    pub pos: Vec2,
+    pub prev_pos: Vec2,
+    pub velocity: Vec2,
+    pub temp_velocity: Vec2,
+    pub size: Vec2,
+    pub health: Health,
+    pub behavior: Behavior,
+    pub state: State,
+    /// Only ranged enemies carry one
+    pub gun: Option<Gun>,
+    acceleration: f32,
+    max_speed: f32,
+    wander_direction: Vec2,
+    wander_timer: Timer,
+    // Runs from the last time the player was in sight
+    last_seen: Timer,
+    last_seen_at: Vec2,
+    // Keeps ranged enemies from firing every time their gun is ready
+    attack_timer: Timer,
+}
+
+impl Enemy {
+    pub fn new(id: u32, x: f32, y: f32, behavior: Behavior) -> Self {
+        let mut last_seen = Timer::new(MEMORY);
+        last_seen.finish();
+
+        Enemy {
+            id,
+            pos: Vec2::new(x, y),
+            prev_pos: Vec2::new(x, y),
+            velocity: Vec2::ZERO,
+            temp_velocity: Vec2::ZERO,
+            size: Vec2::new(8.0, 8.0),
+            health: Health::new(3.0, 0.2),
+            behavior,
+            state: State::Idle,
+            gun: None,
+            acceleration: 240.0,
+            max_speed: 1.5,
+            wander_direction: Vec2::ZERO,
+            wander_timer: Timer::new(0.0),
+            last_seen,
+            last_seen_at: Vec2::ZERO,
+            attack_timer: Timer::new(1.0),
+        }
+    }
+
+    /// Builds an enemy from an `enemy` object in the map. Ranged enemies use the weapon named
+    /// by the `weapon` property, which they must have.
+    pub fn from_object(object: &MapObject, weapons: &[Weapon]) -> Result<Self, String> {
+        let behavior = Behavior::from_name(object.str_property("behavior").unwrap_or("wander"))?;
+        let mut enemy = Enemy::new(object.id, object.pos.x, object.pos.y, behavior);
+
+        if behavior == Behavior::Ranged {
+            let name = object
+                .str_property("weapon")
+                .ok_or("ranged enemy has no 'weapon' property")?;
+            let weapon = weapons
+                .iter()
+                .find(|weapon| weapon.name == name)
+                .ok_or(format!("unknown weapon '{}'", name))?;
+            enemy.gun = Some(Gun::new(vec![weapon.clone()], Team::Enemy));
+        }
+        Ok(enemy)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.pos + self.size / 2.0
+    }
+
+    pub fn hurtbox(&self) -> Hurtbox {
+        Hurtbox {
+            position: self.center(),
+            radius: self.size.x / 2.0,
+            team: Team::Enemy,
+            owner: self.id,
+        }
+    }
+
+    /// Picks a state from what the enemy can see of the player, then moves and shoots. Returns
+    /// the events from its gun, checked against `hurtboxes`.
+    pub fn update(
+        &mut self,
+        tilemap: &TileMap,
+        player: Vec2,
+        hurtboxes: &[Hurtbox],
+        dt: f32,
+    ) -> Vec<GunEvent> {
+        self.prev_pos = self.pos;
+        self.health.update(dt);
+        self.wander_timer.update(dt);
+        self.last_seen.update(dt);
+        self.attack_timer.update(dt);
+
+        let center = self.center();
+        if center.distance(player) <= SIGHT_RANGE && tilemap.has_line_of_sight(center, player) {
+            self.last_seen.reset();
+            self.last_seen_at = player;
+        }
+        self.state = self.next_state();
+
+        let to_player = (self.last_seen_at - center).normalize_or_zero();
+        let direction = match self.state {
+            State::Idle | State::Attack => Vec2::ZERO,
+            State::Wander => self.wander(),
+            // Out of sight, so head round the walls to where the player was last seen
+            State::Chase if self.last_seen.time > 0.0 => {
+                match tilemap.find_path(center, self.last_seen_at, &PathOptions::default()) {
+                    Some(path) => {
+                        let waypoint = path.get(1).copied().unwrap_or(self.last_seen_at);
+                        (waypoint - center).normalize_or_zero()
+                    }
+                    None => to_player,
+                }
+            }
+            State::Chase => to_player,
+            State::Flee => -to_player,
+        };
+        // Ranged enemies keep their distance while shooting
+        let direction = if self.state == State::Attack {
+            let distance = center.distance(self.last_seen_at);
+            if distance < PREFERRED_RANGE * 0.8 {
+                -to_player
+            } else if distance > PREFERRED_RANGE * 1.2 {
+                to_player
+            } else {
+                Vec2::ZERO
+            }
+        } else {
+            direction
+        };
+
+        self.velocity += direction * self.acceleration * dt;
+        self.velocity /= 1.15;
+        self.velocity = self.velocity.clamp_length_max(self.max_speed);
+        self.move_object(tilemap, dt);
+
+        let center = self.center();
+        let Some(gun) = &mut self.gun else {
+            return Vec::new();
+        };
+        let attacking = self.state == State::Attack && self.last_seen.time == 0.0;
+        if attacking && self.attack_timer.is_finished() {
+            gun.pull_trigger(true, true);
+            self.attack_timer.reset();
+        }
+        gun.update(
+            tilemap,
+            hurtboxes,
+            center.into(),
+            self.last_seen_at.into(),
+            dt,
+        )
+    }
+
+    fn next_state(&self) -> State {
+        let alert = !self.last_seen.is_finished();
+        match self.behavior {
+            Behavior::Idle => State::Idle,
+            _ if !alert => State::Wander,
+            Behavior::Wander => State::Wander,
+            Behavior::Chase => State::Chase,
+            Behavior::Flee => State::Flee,
+            Behavior::Ranged => State::Attack,
+        }
+    }
+
+    // Heads off in a random direction every so often, sometimes stopping for a bit
+    fn wander(&mut self) -> Vec2 {
+        if self.wander_timer.is_finished() {
+            self.wander_timer = Timer::new(1.0 + rand::random::<f32>());
+            self.wander_direction = if rand::random::<f32>() < 0.3 {
+                Vec2::ZERO
+            } else {
+                Vec2::from_angle(rand::random::<f32>() * std::f32::consts::TAU)
+            };
+        }
+        self.wander_direction * 0.5
+    }
+
+    fn move_object(&mut self, tilemap: &TileMap, dt: f32) {
+        self.temp_velocity += self.velocity.abs() * dt * 60.0;
+        let direction = self.velocity.signum();
+
+        while self.temp_velocity.y >= 1.0 {
+            let next = self.pos + Vec2::new(0.0, direction.y);
+            if top_down::collides(tilemap, next, self.size) {
+                self.velocity.y = 0.0;
+                // Walked into a wall, try somewhere else
+                self.wander_timer.finish();
+            } else {
+                self.pos = next;
+            }
+            self.temp_velocity.y -= 1.0;
+        }
+
+        while self.temp_velocity.x >= 1.0 {
+            let next = self.pos + Vec2::new(direction.x, 0.0);
+            if top_down::collides(tilemap, next, self.size) {
+                self.velocity.x = 0.0;
+                self.wander_timer.finish();
+            } else {
+                self.pos = next;
+            }
+            self.temp_velocity.x -= 1.0;
+        }
+    }
+
+    pub fn draw(&self, draw: &mut Draw, alpha: f32) {
+        let pos = self.prev_pos.lerp(self.pos, alpha);
+        let color = if self.health.is_invulnerable() {
+            Color::WHITE
+        } else {
+            match self.state {
+                State::Idle | State::Wander => Color::PURPLE,
+                State::Chase | State::Attack => Color::RED,
+                State::Flee => Color::YELLOW,
+            }
+        };
+        draw.rect((pos.x, pos.y), (self.size.x, self.size.y))
+            .color(color);
+
+        if let Some(gun) = &self.gun {
+            gun.draw_bullets(draw, alpha);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::ObjectKind;
+
+    #[test]
+    fn ranged_enemies_need_a_known_weapon() {
+        let tilemap = TileMap::from_tiled(include_str!("../assets/map.json")).unwrap();
+        let weapons = Weapon::load_all(include_str!("../assets/weapons.json")).unwrap();
+        let enemies: Vec<&MapObject> = tilemap
+            .objects
+            .iter()
+            .filter(|o| matches!(o.kind, ObjectKind::Enemy))
+            .collect();
+        for object in &enemies {
+            assert!(Enemy::from_object(object, &weapons).is_ok());
+        }
+
+        let mut ranged = enemies
+            .into_iter()
+            .find(|o| o.str_property("behavior") == Some("ranged"))
+            .unwrap()
+            .clone();
+        ranged
+            .properties
+            .insert("weapon".to_string(), "Slingshot".into());
+        let err = Enemy::from_object(&ranged, &weapons).err().unwrap();
+        assert_eq!(err, "unknown weapon 'Slingshot'");
+
+        ranged.properties.remove("weapon");
+        assert!(Enemy::from_object(&ranged, &weapons).is_err());
+    }
+}
@@ -1,61 +1,587 @@
+use crate::combat::{self, Damage, DamageEvent, Hurtbox, Team};
+use crate::raycast::RayHit;
+use crate::tilemap::{TileMap, TileType};
+use crate::timer::Timer;
 use notan::{
     app::Color,
     draw::{Draw, DrawShapes},
     math::Vec2,
 };
+use serde::Deserialize;
+
+/// What a bullet does when it hits a wall.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactResponse {
+    Destroy,
+    /// Reflects off the surface it hit
+    Bounce,
+    /// Stops dead and stays put until its life runs out
+    Stick,
+}
+
+/// A bullet hitting a wall, reported by `Gun::update` for effects to hook into.
+#[derive(Clone, Copy, Debug)]
+pub struct Impact {
+    pub position: Vec2,
+    /// Points out of the surface that was hit
+    pub normal: Vec2,
+    /// Velocity of the bullet just before it hit
+    pub velocity: Vec2,
+}
+
+pub enum GunEvent {
+    /// A shot left the barrel, the shooter gets pushed back by `recoil` against `direction`
+    Fired {
+        direction: Vec2,
+        recoil: f32,
+        shake: f32,
+    },
+    Impact(Impact),
+    /// A bullet or explosion reached one of the hurtboxes passed to `Gun::update`. `velocity`
+    /// is the bullet's, zero for explosions.
+    Hit {
+        hit: DamageEvent,
+        velocity: Vec2,
+    },
+    /// A bullet with `Modifier::Explode` died here, hits from it are reported separately
+    Explosion {
+        position: Vec2,
+        radius: f32,
+    },
+}
+
+/// Changes to how a bullet flies, listed per weapon. They stack, so a bullet can both home in
+/// and split, and each one only touches its own part of the update.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modifier {
+    /// Passes through this many hurtboxes, dying on the next one
+    Pierce(u32),
+    /// Turns towards the nearest enemy hurtbox within `range` pixels and a `cone` in degrees around
+    /// its heading, by at most `turn_rate` radians per second
+    Homing {
+        cone: f32,
+        range: f32,
+        turn_rate: f32,
+    },
+    /// Reflects off this many walls before the weapon's impact response applies
+    Bounce(u32),
+    /// Bursts into `count` plain fragments, spread evenly around, when the bullet dies
+    Split {
+        count: u32,
+        speed: f32,
+        lifetime: f32,
+    },
+    /// Damages every enemy hurtbox within this radius when the bullet dies
+    Explode { radius: f32 },
+}
+
+/// One weapon, loaded from `assets/weapons.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Weapon {
+    pub name: String,
+    /// Trigger pulls per second
+    pub fire_rate: f32,
+    /// Keeps firing while the trigger is held, otherwise every shot needs its own press
+    #[serde(default)]
+    pub automatic: bool,
+    /// Shots fired by one pull of the trigger
+    #[serde(default = "one")]
+    pub burst_count: u32,
+    /// Seconds between the shots of a burst
+    #[serde(default)]
+    pub burst_interval: f32,
+    /// Bullets per shot, all sharing one round of ammo
+    #[serde(default = "one")]
+    pub pellets: u32,
+    /// Width of the cone pellets scatter in, in degrees
+    #[serde(default)]
+    pub spread: f32,
+    pub magazine_size: u32,
+    pub reload_time: f32,
+    pub projectile_speed: f32,
+    pub projectile_lifetime: f32,
+    pub projectile_radius: f32,
+    #[serde(default)]
+    pub recoil: f32,
+    #[serde(default)]
+    pub screen_shake: f32,
+    pub impact_response: ImpactResponse,
+    /// Per bullet, so a shotgun blast that fully lands does `damage * pellets`
+    #[serde(default = "one_f32")]
+    pub damage: f32,
+    #[serde(default)]
+    pub knockback: f32,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn one_f32() -> f32 {
+    1.0
+}
+
+impl Weapon {
+    pub fn load_all(json: &str) -> Result<Vec<Weapon>, String> {
+        let weapons: Vec<Weapon> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if weapons.is_empty() {
+            return Err("no weapons defined".to_string());
+        }
+        for weapon in &weapons {
+            weapon.validate()?;
+        }
+        Ok(weapons)
+    }
+
+    // Values that would divide by zero or never fire a bullet
+    fn validate(&self) -> Result<(), String> {
+        let problem = if self.fire_rate <= 0.0 {
+            "fire_rate must be above zero"
+        } else if self.magazine_size == 0 {
+            "magazine_size must be at least 1"
+        } else if self.pellets == 0 {
+            "pellets must be at least 1"
+        } else if self.burst_count == 0 {
+            "burst_count must be at least 1"
+        } else {
+            return Ok(());
+        };
+        Err(format!("weapon '{}': {}", self.name, problem))
+    }
+}
 
 pub struct Bullet {
     pub position: Vec2,
+    pub prev_position: Vec2,
     pub velocity: Vec2,
     pub life: f32,
+    pub radius: f32,
+    pub response: ImpactResponse,
+    pub modifiers: Vec<Modifier>,
+    /// Whose bullet it is, it passes through that side's hurtboxes
+    pub team: Team,
+    pub damage: f32,
+    pub knockback: f32,
+    pub dead: bool,
+    // Hit a wall with `ImpactResponse::Stick`, it's scenery until its life runs out
+    stuck: bool,
+    pierce_left: u32,
+    bounces_left: u32,
+    // Owners of the hurtboxes already hit, so piercing bullets don't hit the same one every tick
+    hit: Vec<u32>,
+}
+
+impl Bullet {
+    pub fn new(
+        position: Vec2,
+        velocity: Vec2,
+        life: f32,
+        radius: f32,
+        response: ImpactResponse,
+        modifiers: Vec<Modifier>,
+    ) -> Self {
+        let mut pierce_left = 0;
+        let mut bounces_left = 0;
+        for modifier in &modifiers {
+            match modifier {
+                Modifier::Pierce(count) => pierce_left += count,
+                Modifier::Bounce(count) => bounces_left += count,
+                _ => {}
+            }
+        }
+
+        Bullet {
+            position,
+            prev_position: position,
+            velocity,
+            life,
+            radius,
+            response,
+            modifiers,
+            team: Team::Player,
+            damage: 1.0,
+            knockback: 0.0,
+            dead: false,
+            stuck: false,
+            pierce_left,
+            bounces_left,
+            hit: Vec::new(),
+        }
+    }
+
+    fn steer(&mut self, hurtboxes: &[Hurtbox], dt: f32) {
+        for modifier in &self.modifiers {
+            let Modifier::Homing {
+                cone,
+                range,
+                turn_rate,
+            } = *modifier
+            else {
+                continue;
+            };
+            let speed = self.velocity.length();
+            if speed == 0.0 {
+                return;
+            }
+
+            let heading = self.velocity / speed;
+            let nearest = hurtboxes
+                .iter()
+                .filter(|hurtbox| hurtbox.team != self.team)
+                .map(|hurtbox| hurtbox.position - self.position)
+                .filter(|to| {
+                    to.length() <= range
+                        && heading.angle_between(*to).abs() <= cone.to_radians() / 2.0
+                })
+                .min_by(|a, b| a.length().total_cmp(&b.length()));
+
+            if let Some(to) = nearest {
+                let turn = heading
+                    .angle_between(to)
+                    .clamp(-turn_rate * dt, turn_rate * dt);
+                self.velocity = Vec2::from_angle(turn).rotate(self.velocity);
+            }
+        }
+    }
+
+    fn hit_hurtboxes(&mut self, hurtboxes: &[Hurtbox], events: &mut Vec<GunEvent>) {
+        // Only the tick it flew in on can hit, not whatever walks into it afterwards
+        if self.stuck && self.position == self.prev_position {
+            return;
+        }
+        for (i, hurtbox) in hurtboxes.iter().enumerate() {
+            if self.dead || hurtbox.team == self.team || self.hit.contains(&hurtbox.owner) {
+                continue;
+            }
+            let distance = distance_to_segment(hurtbox.position, self.prev_position, self.position);
+            if distance > hurtbox.radius + self.radius {
+                continue;
+            }
+
+            self.hit.push(hurtbox.owner);
+            events.push(GunEvent::Hit {
+                hit: DamageEvent {
+                    target: i,
+                    position: self.position,
+                    damage: Damage {
+                        amount: self.damage,
+                        knockback: self.velocity.normalize_or_zero() * self.knockback,
+                    },
+                },
+                velocity: self.velocity,
+            });
+            if self.pierce_left == 0 {
+                self.dead = true;
+            } else {
+                self.pierce_left -= 1;
+            }
+        }
+    }
+
+    // Explosions and fragments left behind by a bullet that just died
+    fn on_death(
+        &self,
+        hurtboxes: &[Hurtbox],
+        events: &mut Vec<GunEvent>,
+        spawned: &mut Vec<Bullet>,
+    ) {
+        for modifier in &self.modifiers {
+            match *modifier {
+                Modifier::Explode { radius } => {
+                    events.push(GunEvent::Explosion {
+                        position: self.position,
+                        radius,
+                    });
+                    for i in combat::overlapping(hurtboxes, self.position, radius, self.team) {
+                        let away = (hurtboxes[i].position - self.position).normalize_or_zero();
+                        events.push(GunEvent::Hit {
+                            hit: DamageEvent {
+                                target: i,
+                                position: hurtboxes[i].position,
+                                damage: Damage {
+                                    amount: self.damage,
+                                    knockback: away * self.knockback,
+                                },
+                            },
+                            velocity: Vec2::ZERO,
+                        });
+                    }
+                }
+                Modifier::Split {
+                    count,
+                    speed,
+                    lifetime,
+                } => {
+                    let start = self.velocity.y.atan2(self.velocity.x);
+                    for i in 0..count {
+                        let angle = start + std::f32::consts::TAU * i as f32 / count as f32;
+                        let mut fragment = Bullet::new(
+                            self.position,
+                            Vec2::from_angle(angle) * speed,
+                            lifetime,
+                            self.radius / 2.0,
+                            ImpactResponse::Destroy,
+                            Vec::new(),
+                        );
+                        fragment.team = self.team;
+                        fragment.damage = self.damage / 2.0;
+                        fragment.knockback = self.knockback / 2.0;
+                        spawned.push(fragment);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Walks the path one pixel at a time so fast bullets can't skip over a thin wall
+    fn sweep(&mut self, tilemap: &TileMap, dt: f32) -> Option<Impact> {
+        let delta = self.velocity * dt;
+        let steps = delta.length().ceil().max(1.0) as u32;
+        let step = delta / steps as f32;
+
+        for _ in 0..steps {
+            let next = self.position + step;
+            if !tilemap.is_pixel_solid(next.x, next.y) {
+                self.position = next;
+                continue;
+            }
+
+            let impact = Impact {
+                position: self.position,
+                normal: surface_normal(tilemap, self.position, step),
+                velocity: self.velocity,
+            };
+            if self.bounces_left > 0 {
+                self.bounces_left -= 1;
+                let n = impact.normal;
+                self.velocity -= 2.0 * self.velocity.dot(n) * n;
+                return Some(impact);
+            }
+            match self.response {
+                ImpactResponse::Destroy => self.dead = true,
+                ImpactResponse::Bounce => {
+                    let n = impact.normal;
+                    self.velocity -= 2.0 * self.velocity.dot(n) * n;
+                }
+                ImpactResponse::Stick => {
+                    self.velocity = Vec2::ZERO;
+                    self.stuck = true;
+                }
+            }
+            return Some(impact);
+        }
+        None
+    }
+}
+
+fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_squared() > 0.0 {
+        ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+// Normal of the surface hit when stepping from `from` by `step`
+fn surface_normal(tilemap: &TileMap, from: Vec2, step: Vec2) -> Vec2 {
+    let to = from + step;
+    let slope_normal = match tilemap.get_tile_type(to.x, to.y) {
+        TileType::SlopeUpRight => Some(Vec2::new(-1.0, -1.0).normalize()),
+        TileType::SlopeUpLeft => Some(Vec2::new(1.0, -1.0).normalize()),
+        _ => None,
+    };
+    // Only the diagonal face of a slope has the slope's normal, its flat sides act like walls
+    if let Some(normal) = slope_normal.filter(|normal| normal.dot(step) < 0.0) {
+        return normal;
+    }
+
+    if tilemap.is_pixel_solid(to.x, from.y) {
+        Vec2::new(-step.x.signum(), 0.0)
+    } else if tilemap.is_pixel_solid(from.x, to.y) {
+        Vec2::new(0.0, -step.y.signum())
+    } else {
+        // Straight into a corner
+        -step.signum().normalize()
+    }
 }
 
 pub struct Gun {
     pub angle: f32,
     pub aim_line_length: f32,
+    /// The wall the aim line stops at, if it doesn't reach the cursor
+    pub aim_hit: Option<RayHit>,
     pub shoot_radius: f32,
     pub bullets: Vec<Bullet>,
+    pub weapons: Vec<Weapon>,
+    pub current: usize,
+    /// Rounds left in each weapon's magazine, kept while the weapon is put away
+    pub ammo: Vec<u32>,
+    pub reloading: bool,
+    pub team: Team,
+    cooldown: Timer,
+    reload_timer: Timer,
+    burst_left: u32,
+    burst_timer: Timer,
 }
 
 impl Gun {
-    pub fn new() -> Self {
+    pub fn new(weapons: Vec<Weapon>, team: Team) -> Self {
+        let ammo = weapons.iter().map(|weapon| weapon.magazine_size).collect();
+
         Gun {
             angle: 0.0,
             aim_line_length: 50.0,
+            aim_hit: None,
             shoot_radius: 5.0,
             bullets: Vec::new(),
+            weapons,
+            current: 0,
+            ammo,
+            reloading: false,
+            team,
+            cooldown: Timer::new(0.0),
+            reload_timer: Timer::new(0.0),
+            burst_left: 0,
+            burst_timer: Timer::new(0.0),
+        }
+    }
+
+    pub fn weapon(&self) -> &Weapon {
+        &self.weapons[self.current]
+    }
+
+    /// Puts the current weapon away mid-burst or mid-reload and takes out the next one.
+    pub fn switch_weapon(&mut self) {
+        self.current = (self.current + 1) % self.weapons.len();
+        self.burst_left = 0;
+        self.reloading = false;
+    }
+
+    pub fn reload(&mut self) {
+        if !self.reloading && self.ammo[self.current] < self.weapon().magazine_size {
+            self.reloading = true;
+            self.reload_timer = Timer::new(self.weapon().reload_time);
+        }
+    }
+
+    /// Starts a burst if the weapon is ready. Automatic weapons fire while `held`,
+    /// the rest only on a fresh press.
+    pub fn pull_trigger(&mut self, pressed: bool, held: bool) {
+        let weapon = &self.weapons[self.current];
+        let wants_to_fire = if weapon.automatic { held } else { pressed };
+        if !wants_to_fire || self.reloading || self.burst_left > 0 || !self.cooldown.is_finished() {
+            return;
+        }
+
+        if self.ammo[self.current] == 0 {
+            self.reload();
+            return;
         }
+
+        self.burst_left = weapon.burst_count;
+        self.cooldown = Timer::new(1.0 / weapon.fire_rate);
+        self.burst_timer = Timer::new(0.0);
     }
 
-    pub fn shoot(&mut self, player_position: (f32, f32)) {
+    fn fire(&mut self, player_position: (f32, f32)) -> GunEvent {
+        let weapon = &self.weapons[self.current];
         let x = player_position.0 + self.angle.cos() * self.shoot_radius;
         let y = player_position.1 + self.angle.sin() * self.shoot_radius;
 
-        self.bullets.push(Bullet {
-            position: Vec2::new(x, y),
-            velocity: Vec2::new(self.angle.cos() * 500.0, self.angle.sin() * 500.0),
-            life: 1.0,
-        });
+        for _ in 0..weapon.pellets {
+            let angle = self.angle + (rand::random::<f32>() - 0.5) * weapon.spread.to_radians();
+            let mut bullet = Bullet::new(
+                Vec2::new(x, y),
+                Vec2::from_angle(angle) * weapon.projectile_speed,
+                weapon.projectile_lifetime,
+                weapon.projectile_radius,
+                weapon.impact_response,
+                weapon.modifiers.clone(),
+            );
+            bullet.team = self.team;
+            bullet.damage = weapon.damage;
+            bullet.knockback = weapon.knockback;
+            self.bullets.push(bullet);
+        }
+        self.ammo[self.current] -= 1;
+
+        GunEvent::Fired {
+            direction: Vec2::from_angle(self.angle),
+            recoil: weapon.recoil,
+            shake: weapon.screen_shake,
+        }
     }
 
-    pub fn update(&mut self, player_position: (f32, f32), mouse_position: (f32, f32), dt: f32) {
+    pub fn update(
+        &mut self,
+        tilemap: &TileMap,
+        hurtboxes: &[Hurtbox],
+        player_position: (f32, f32),
+        mouse_position: (f32, f32),
+        dt: f32,
+    ) -> Vec<GunEvent> {
         let dx = mouse_position.0 - player_position.0;
         let dy = mouse_position.1 - player_position.1;
 
         self.angle = dy.atan2(dx);
-        self.aim_line_length = (dx * dx + dy * dy).sqrt();
+        // The aim line stops at the first wall in the way
+        let origin = Vec2::new(player_position.0, player_position.1);
+        let reach = (dx * dx + dy * dy).sqrt();
+        self.aim_hit = tilemap.raycast(origin, Vec2::new(dx, dy), reach);
+        self.aim_line_length = self.aim_hit.map_or(reach, |hit| hit.distance);
 
-        for bullet in &mut self.bullets {
-            bullet.position += bullet.velocity * dt;
+        let mut events = Vec::new();
+
+        self.cooldown.update(dt);
+        if self.reloading {
+            self.reload_timer.update(dt);
+            if self.reload_timer.is_finished() {
+                self.reloading = false;
+                self.ammo[self.current] = self.weapon().magazine_size;
+            }
+        }
+
+        self.burst_timer.update(dt);
+        if self.burst_left > 0 && self.burst_timer.is_finished() {
+            if self.ammo[self.current] == 0 {
+                self.burst_left = 0;
+                self.reload();
+            } else {
+                events.push(self.fire(player_position));
+                self.burst_left -= 1;
+                self.burst_timer = Timer::new(self.weapon().burst_interval);
+            }
         }
 
-        self.bullets.retain_mut(|bullet| {
+        let mut spawned = Vec::new();
+        for bullet in &mut self.bullets {
+            bullet.prev_position = bullet.position;
+            bullet.steer(hurtboxes, dt);
+            events.extend(bullet.sweep(tilemap, dt).map(GunEvent::Impact));
+            bullet.hit_hurtboxes(hurtboxes, &mut events);
+
             bullet.life -= dt;
-            bullet.life > 0.0
-        });
+            if bullet.life <= 0.0 {
+                bullet.dead = true;
+            }
+            if bullet.dead {
+                bullet.on_death(hurtboxes, &mut events, &mut spawned);
+            }
+        }
+
+        self.bullets.retain(|bullet| !bullet.dead);
+        self.bullets.extend(spawned);
+        events
     }
 
-    pub fn draw(&self, draw: &mut Draw, player_position: (f32, f32)) {
+    pub fn draw(&self, draw: &mut Draw, player_position: (f32, f32), alpha: f32) {
         let x = player_position.0 + self.angle.cos() * self.aim_line_length;
         let y = player_position.1 + self.angle.sin() * self.aim_line_length;
 
@@ -63,11 +589,132 @@ impl Gun {
             .width(2.0)
             .color(Color::YELLOW)
             .alpha(0.2);
+        if let Some(hit) = self.aim_hit {
+            let tick = hit.point + hit.normal * 3.0;
+            draw.line(hit.point.into(), tick.into())
+                .width(2.0)
+                .color(Color::YELLOW)
+                .alpha(0.5);
+        }
 
+        self.draw_bullets(draw, alpha);
+    }
+
+    /// Just the bullets, for guns held by something without an aim line.
+    pub fn draw_bullets(&self, draw: &mut Draw, alpha: f32) {
         for bullet in &self.bullets {
-            draw.circle(5.0)
-                .position(bullet.position.x, bullet.position.y)
+            let position = bullet.prev_position.lerp(bullet.position, alpha);
+            draw.circle(bullet.radius)
+                .position(position.x, position.y)
                 .color(Color::RED);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(x: f32, owner: u32) -> Hurtbox {
+        Hurtbox {
+            position: Vec2::new(x, 0.0),
+            radius: 4.0,
+            team: Team::Enemy,
+            owner,
+        }
+    }
+
+    fn hits(bullet: &mut Bullet, hurtboxes: &[Hurtbox]) -> Vec<usize> {
+        let mut events = Vec::new();
+        bullet.hit_hurtboxes(hurtboxes, &mut events);
+        events
+            .iter()
+            .filter_map(|event| match event {
+                GunEvent::Hit { hit, .. } => Some(hit.target),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn pistol(field: &str, value: serde_json::Value) -> String {
+        let mut weapon = serde_json::json!({
+            "name": "Pistol",
+            "fire_rate": 5.0,
+            "magazine_size": 12,
+            "reload_time": 0.9,
+            "projectile_speed": 500.0,
+            "projectile_lifetime": 1.0,
+            "projectile_radius": 3.0,
+            "impact_response": "destroy"
+        });
+        weapon[field] = value;
+        serde_json::json!([weapon]).to_string()
+    }
+
+    #[test]
+    fn weapons_that_cant_fire_are_rejected() {
+        assert!(Weapon::load_all(&pistol("fire_rate", 5.0.into())).is_ok());
+        assert!(Weapon::load_all("[]").is_err());
+
+        for (field, value) in [
+            ("fire_rate", 0.0.into()),
+            ("fire_rate", (-1.0).into()),
+            ("magazine_size", 0.into()),
+            ("pellets", 0.into()),
+            ("burst_count", 0.into()),
+        ] {
+            let err = Weapon::load_all(&pistol(field, value)).unwrap_err();
+            assert!(err.contains(field), "{}", err);
+        }
+    }
+
+    #[test]
+    fn piercing_bullets_remember_owners_not_indices() {
+        let velocity = Vec2::new(10.0, 0.0);
+        let modifiers = vec![Modifier::Pierce(2)];
+        let mut bullet = Bullet::new(
+            Vec2::ZERO,
+            velocity,
+            1.0,
+            1.0,
+            ImpactResponse::Destroy,
+            modifiers,
+        );
+        assert_eq!(
+            hits(&mut bullet, &[target(0.0, 1), target(20.0, 2)]),
+            vec![0]
+        );
+
+        // Still overlapping the first target, which isn't hit again
+        bullet.prev_position = bullet.position;
+        bullet.position += Vec2::new(2.0, 0.0);
+        assert!(hits(&mut bullet, &[target(0.0, 1), target(20.0, 2)]).is_empty());
+
+        // The first target died and the second moved up to its index
+        bullet.prev_position = bullet.position;
+        bullet.position = Vec2::new(20.0, 0.0);
+        assert_eq!(hits(&mut bullet, &[target(20.0, 2)]), vec![0]);
+    }
+
+    #[test]
+    fn stuck_bullets_dont_hit_anything() {
+        let mut tilemap = TileMap::new(4, 1);
+        tilemap.set_tile(2, 0, TileType::Solid);
+        let mut bullet = Bullet::new(
+            Vec2::new(8.0, 0.0),
+            Vec2::new(200.0, 0.0),
+            1.0,
+            1.0,
+            ImpactResponse::Stick,
+            Vec::new(),
+        );
+        assert!(bullet.sweep(&tilemap, 0.2).is_some());
+        assert_eq!(bullet.position, Vec2::new(31.0, 0.0));
+
+        // Something walks into the bullet sticking out of the wall
+        bullet.prev_position = bullet.position;
+        assert!(bullet.sweep(&tilemap, 0.2).is_none());
+        assert!(hits(&mut bullet, &[target(30.0, 1)]).is_empty());
+        assert!(!bullet.dead);
+    }
+}
@@ -0,0 +1,124 @@
+//! A graphics device that draws nothing, so sprite sheets and tilemaps can be built in tests.
+use crate::aseprite::SpriteSheet;
+use notan::graphics::buffer::{IndexFormat, VertexAttr, VertexStepMode};
+use notan::graphics::commands::Commands;
+use notan::graphics::pipeline::PipelineOptions;
+use notan::graphics::*;
+use notan::prelude::Graphics;
+
+#[derive(Default)]
+struct NullBackend {
+    next_id: u64,
+}
+
+impl NullBackend {
+    fn id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+impl DeviceBackend for NullBackend {
+    fn api_name(&self) -> &str {
+        "null"
+    }
+
+    fn stats(&self) -> GpuStats {
+        GpuStats::default()
+    }
+
+    fn reset_stats(&mut self) {}
+
+    fn create_pipeline(
+        &mut self,
+        _vertex_source: &[u8],
+        _fragment_source: &[u8],
+        _vertex_attrs: &[VertexAttr],
+        _texture_locations: &[(u32, String)],
+        _options: PipelineOptions,
+    ) -> Result<u64, String> {
+        Ok(self.id())
+    }
+
+    fn create_vertex_buffer(
+        &mut self,
+        _attrs: &[VertexAttr],
+        _step_mode: VertexStepMode,
+    ) -> Result<u64, String> {
+        Ok(self.id())
+    }
+
+    fn create_index_buffer(&mut self, _format: IndexFormat) -> Result<u64, String> {
+        Ok(self.id())
+    }
+
+    fn create_uniform_buffer(&mut self, _slot: u32, _name: &str) -> Result<u64, String> {
+        Ok(self.id())
+    }
+
+    fn set_buffer_data(&mut self, _buffer: u64, _data: &[u8]) {}
+
+    fn render(&mut self, _commands: &[Commands], _target: Option<u64>) {}
+
+    fn clean(&mut self, _to_clean: &[ResourceId]) {}
+
+    fn set_size(&mut self, _width: u32, _height: u32) {}
+
+    fn set_dpi(&mut self, _scale_factor: f64) {}
+
+    fn create_texture(
+        &mut self,
+        _source: TextureSourceKind,
+        info: TextureInfo,
+    ) -> Result<(u64, TextureInfo), String> {
+        Ok((self.id(), info))
+    }
+
+    fn create_render_texture(
+        &mut self,
+        _texture_id: u64,
+        _info: &TextureInfo,
+    ) -> Result<u64, String> {
+        Ok(self.id())
+    }
+
+    fn update_texture(
+        &mut self,
+        _texture: u64,
+        _source: TextureUpdaterSourceKind,
+        _opts: TextureUpdate,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn read_pixels(
+        &mut self,
+        _texture: u64,
+        _bytes: &mut [u8],
+        _opts: &TextureRead,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+pub fn graphics() -> Graphics {
+    Graphics::new(Box::<NullBackend>::default()).unwrap()
+}
+
+/// The player's sheet, loaded the same way as in `Assets::load`
+pub fn skeletron() -> SpriteSheet {
+    let mut sheet = SpriteSheet::from_aseprite(
+        &mut graphics(),
+        include_str!("../assets/skeletron.json"),
+        include_bytes!("../assets/skeletron.png"),
+    )
+    .unwrap();
+    sheet
+        .extend(include_str!("../assets/skeletron.meta.json"))
+        .unwrap();
+    sheet
+}
@@ -0,0 +1,632 @@
+use gilrs::Gilrs;
+use notan::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Axis values closer to zero than this count as released
+const DEAD_ZONE: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Jump,
+    Shoot,
+    AdvanceDialogue,
+    Sprint,
+    Pause,
+    Confirm,
+    Undo,
+    Redo,
+    Restart,
+    Hint,
+    SwitchWeapon,
+    Reload,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    MoveX,
+    MoveY,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    const ALL: [GamepadButton; 14] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+
+    // gilrs calls the shoulder buttons triggers and the triggers second triggers
+    fn to_gilrs(self) -> gilrs::Button {
+        match self {
+            GamepadButton::South => gilrs::Button::South,
+            GamepadButton::East => gilrs::Button::East,
+            GamepadButton::West => gilrs::Button::West,
+            GamepadButton::North => gilrs::Button::North,
+            GamepadButton::LeftShoulder => gilrs::Button::LeftTrigger,
+            GamepadButton::RightShoulder => gilrs::Button::RightTrigger,
+            GamepadButton::LeftTrigger => gilrs::Button::LeftTrigger2,
+            GamepadButton::RightTrigger => gilrs::Button::RightTrigger2,
+            GamepadButton::Select => gilrs::Button::Select,
+            GamepadButton::Start => gilrs::Button::Start,
+            GamepadButton::DPadUp => gilrs::Button::DPadUp,
+            GamepadButton::DPadDown => gilrs::Button::DPadDown,
+            GamepadButton::DPadLeft => gilrs::Button::DPadLeft,
+            GamepadButton::DPadRight => gilrs::Button::DPadRight,
+        }
+    }
+
+    fn from_gilrs(button: gilrs::Button) -> Option<Self> {
+        GamepadButton::ALL
+            .into_iter()
+            .find(|ours| ours.to_gilrs() == button)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+impl GamepadAxis {
+    const ALL: [GamepadAxis; 4] = [
+        GamepadAxis::LeftStickX,
+        GamepadAxis::LeftStickY,
+        GamepadAxis::RightStickX,
+        GamepadAxis::RightStickY,
+    ];
+
+    // gilrs has up as positive on the Y axes, the game has down as positive like the screen
+    fn read(self, gamepad: &gilrs::Gamepad) -> f32 {
+        match self {
+            GamepadAxis::LeftStickX => gamepad.value(gilrs::Axis::LeftStickX),
+            GamepadAxis::LeftStickY => -gamepad.value(gilrs::Axis::LeftStickY),
+            GamepadAxis::RightStickX => gamepad.value(gilrs::Axis::RightStickX),
+            GamepadAxis::RightStickY => -gamepad.value(gilrs::Axis::RightStickY),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButton),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisBinding {
+    Keys {
+        negative: KeyCode,
+        positive: KeyCode,
+    },
+    GamepadButtons {
+        negative: GamepadButton,
+        positive: GamepadButton,
+    },
+    GamepadAxis(GamepadAxis),
+}
+
+/// Which inputs drive each action, several bindings per action are allowed.
+/// Stored as JSON so players can rebind controls by editing the file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bindings {
+    pub actions: HashMap<Action, Vec<Binding>>,
+    pub axes: HashMap<Axis, Vec<AxisBinding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let actions = HashMap::from([
+            (
+                Action::Jump,
+                vec![
+                    Binding::Key(KeyCode::Space),
+                    Binding::GamepadButton(GamepadButton::South),
+                ],
+            ),
+            (
+                Action::Shoot,
+                vec![
+                    Binding::Mouse(MouseButton::Left),
+                    Binding::GamepadButton(GamepadButton::RightTrigger),
+                ],
+            ),
+            (
+                Action::AdvanceDialogue,
+                vec![
+                    Binding::Key(KeyCode::Space),
+                    Binding::Key(KeyCode::Return),
+                    Binding::GamepadButton(GamepadButton::South),
+                ],
+            ),
+            (
+                Action::Sprint,
+                vec![
+                    Binding::Key(KeyCode::LShift),
+                    Binding::GamepadButton(GamepadButton::West),
+                ],
+            ),
+            (
+                Action::Pause,
+                vec![
+                    Binding::Key(KeyCode::Escape),
+                    Binding::GamepadButton(GamepadButton::Start),
+                ],
+            ),
+            (
+                Action::Confirm,
+                vec![
+                    Binding::Key(KeyCode::Return),
+                    Binding::Key(KeyCode::Space),
+                    Binding::GamepadButton(GamepadButton::South),
+                ],
+            ),
+            (
+                Action::Undo,
+                vec![
+                    Binding::Key(KeyCode::Z),
+                    Binding::Key(KeyCode::Back),
+                    Binding::GamepadButton(GamepadButton::LeftShoulder),
+                ],
+            ),
+            (
+                Action::Redo,
+                vec![
+                    Binding::Key(KeyCode::Y),
+                    Binding::GamepadButton(GamepadButton::RightShoulder),
+                ],
+            ),
+            (
+                Action::Restart,
+                vec![
+                    Binding::Key(KeyCode::R),
+                    Binding::GamepadButton(GamepadButton::Select),
+                ],
+            ),
+            (
+                Action::Hint,
+                vec![
+                    Binding::Key(KeyCode::H),
+                    Binding::GamepadButton(GamepadButton::North),
+                ],
+            ),
+            (
+                Action::SwitchWeapon,
+                vec![
+                    Binding::Key(KeyCode::Q),
+                    Binding::GamepadButton(GamepadButton::East),
+                ],
+            ),
+            (
+                Action::Reload,
+                vec![
+                    Binding::Key(KeyCode::R),
+                    Binding::GamepadButton(GamepadButton::LeftTrigger),
+                ],
+            ),
+        ]);
+
+        let axes = HashMap::from([
+            (
+                Axis::MoveX,
+                vec![
+                    AxisBinding::Keys {
+                        negative: KeyCode::A,
+                        positive: KeyCode::D,
+                    },
+                    AxisBinding::Keys {
+                        negative: KeyCode::Left,
+                        positive: KeyCode::Right,
+                    },
+                    AxisBinding::GamepadButtons {
+                        negative: GamepadButton::DPadLeft,
+                        positive: GamepadButton::DPadRight,
+                    },
+                    AxisBinding::GamepadAxis(GamepadAxis::LeftStickX),
+                ],
+            ),
+            (
+                Axis::MoveY,
+                vec![
+                    AxisBinding::Keys {
+                        negative: KeyCode::W,
+                        positive: KeyCode::S,
+                    },
+                    AxisBinding::Keys {
+                        negative: KeyCode::Up,
+                        positive: KeyCode::Down,
+                    },
+                    AxisBinding::GamepadButtons {
+                        negative: GamepadButton::DPadUp,
+                        positive: GamepadButton::DPadDown,
+                    },
+                    AxisBinding::GamepadAxis(GamepadAxis::LeftStickY),
+                ],
+            ),
+        ]);
+
+        Bindings { actions, axes }
+    }
+}
+
+impl Bindings {
+    /// Actions missing from the file, e.g. ones added since it was saved, keep their defaults.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut bindings: Bindings = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+
+        let defaults = Bindings::default();
+        for (action, default) in defaults.actions {
+            bindings.actions.entry(action).or_insert(default);
+        }
+        for (axis, default) in defaults.axes {
+            bindings.axes.entry(axis).or_insert(default);
+        }
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Every connected gamepad merged into one, notan doesn't report gamepads so this comes from
+/// gilrs.
+#[derive(Default)]
+pub struct GamepadState {
+    pub buttons: HashSet<GamepadButton>,
+    /// Pressed since the last sample, even if already let go
+    pub pressed: HashSet<GamepadButton>,
+    pub axes: HashMap<GamepadAxis, f32>,
+}
+
+impl GamepadState {
+    fn poll(&mut self, gilrs: &mut Gilrs) {
+        while let Some(event) = gilrs.next_event() {
+            if let gilrs::EventType::ButtonPressed(button, _) = event.event {
+                self.pressed.extend(GamepadButton::from_gilrs(button));
+            }
+        }
+
+        self.buttons.clear();
+        self.axes.clear();
+        for (_, gamepad) in gilrs.gamepads() {
+            for button in GamepadButton::ALL {
+                if gamepad.is_pressed(button.to_gilrs()) {
+                    self.buttons.insert(button);
+                }
+            }
+            // The stick pushed furthest wins
+            for axis in GamepadAxis::ALL {
+                let value = axis.read(&gamepad);
+                let current = self.axes.entry(axis).or_default();
+                if value.abs() > current.abs() {
+                    *current = value;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ActionState {
+    pub pressed: bool,
+    pub held: bool,
+    pub released: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AxisState {
+    pub value: f32,
+    /// -1 or 1 when the axis was pushed past the dead zone, 0 otherwise
+    pub pressed: f32,
+}
+
+/// The state of every action at one point in time, handed to the game controllers.
+#[derive(Clone, Debug, Default)]
+pub struct ActionSnapshot {
+    actions: HashMap<Action, ActionState>,
+    axes: HashMap<Axis, AxisState>,
+}
+
+impl ActionSnapshot {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.action(action).pressed
+    }
+
+    pub fn held(&self, action: Action) -> bool {
+        self.action(action).held
+    }
+
+    pub fn released(&self, action: Action) -> bool {
+        self.action(action).released
+    }
+
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.axes.get(&axis).map_or(0.0, |state| state.value)
+    }
+
+    pub fn axis_pressed(&self, axis: Axis) -> f32 {
+        self.axes.get(&axis).map_or(0.0, |state| state.pressed)
+    }
+
+    fn action(&self, action: Action) -> ActionState {
+        self.actions.get(&action).copied().unwrap_or_default()
+    }
+}
+
+pub struct Input {
+    pub bindings: Bindings,
+    pub gamepad: GamepadState,
+    // Missing when the platform has no gamepad support, only keyboard and mouse work then
+    gilrs: Option<Gilrs>,
+    snapshot: ActionSnapshot,
+}
+
+impl Input {
+    pub fn new(bindings: Bindings) -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                eprintln!("gamepads unavailable: {}", e);
+                None
+            }
+        };
+
+        Input {
+            bindings,
+            gamepad: GamepadState::default(),
+            gilrs,
+            snapshot: ActionSnapshot::default(),
+        }
+    }
+
+    /// Samples the devices once per frame. Presses and releases are kept until
+    /// `clear_presses`, so a simulation tick sees them even if it runs a frame later.
+    pub fn update(&mut self, app: &App) {
+        if let Some(gilrs) = &mut self.gilrs {
+            self.gamepad.poll(gilrs);
+        }
+        self.sample(&app.keyboard, &app.mouse);
+        self.gamepad.pressed.clear();
+    }
+
+    fn sample(&mut self, keyboard: &Keyboard, mouse: &Mouse) {
+        let devices = Devices {
+            keyboard,
+            mouse,
+            gamepad: &self.gamepad,
+        };
+        for (&action, bindings) in &self.bindings.actions {
+            let held = bindings.iter().any(|binding| devices.down(binding));
+            let pressed = bindings.iter().any(|binding| devices.pressed(binding));
+
+            let state = self.snapshot.actions.entry(action).or_default();
+            state.pressed |= pressed || (held && !state.held);
+            state.released |= !held && state.held;
+            state.held = held;
+        }
+
+        for (&axis, bindings) in &self.bindings.axes {
+            let value = bindings
+                .iter()
+                .map(|binding| devices.axis(binding))
+                .sum::<f32>()
+                .clamp(-1.0, 1.0);
+
+            let state = self.snapshot.axes.entry(axis).or_default();
+            let was_active = state.value.abs() >= DEAD_ZONE;
+            let active = value.abs() >= DEAD_ZONE;
+            if active && (!was_active || value.signum() != state.value.signum()) {
+                state.pressed = value.signum();
+            }
+            state.value = if active { value } else { 0.0 };
+        }
+    }
+
+    pub fn snapshot(&self) -> ActionSnapshot {
+        self.snapshot.clone()
+    }
+
+    pub fn clear_presses(&mut self) {
+        for state in self.snapshot.actions.values_mut() {
+            state.pressed = false;
+            state.released = false;
+        }
+        for state in self.snapshot.axes.values_mut() {
+            state.pressed = 0.0;
+        }
+    }
+}
+
+struct Devices<'a> {
+    keyboard: &'a Keyboard,
+    mouse: &'a Mouse,
+    gamepad: &'a GamepadState,
+}
+
+impl Devices<'_> {
+    fn down(&self, binding: &Binding) -> bool {
+        match *binding {
+            Binding::Key(key) => self.keyboard.is_down(key),
+            Binding::Mouse(button) => self.mouse.is_down(button),
+            Binding::GamepadButton(button) => self.gamepad.buttons.contains(&button),
+        }
+    }
+
+    // Catches presses shorter than a frame, which `down` alone would miss
+    fn pressed(&self, binding: &Binding) -> bool {
+        match *binding {
+            Binding::Key(key) => self.keyboard.was_pressed(key),
+            Binding::Mouse(button) => self.mouse.was_pressed(button),
+            Binding::GamepadButton(button) => self.gamepad.pressed.contains(&button),
+        }
+    }
+
+    fn axis(&self, binding: &AxisBinding) -> f32 {
+        let pair = |negative: bool, positive: bool| positive as i32 as f32 - negative as i32 as f32;
+        match *binding {
+            AxisBinding::Keys { negative, positive } => pair(
+                self.keyboard.is_down(negative),
+                self.keyboard.is_down(positive),
+            ),
+            AxisBinding::GamepadButtons { negative, positive } => pair(
+                self.gamepad.buttons.contains(&negative),
+                self.gamepad.buttons.contains(&positive),
+            ),
+            AxisBinding::GamepadAxis(axis) => self.gamepad.axes.get(&axis).copied().unwrap_or(0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() -> Input {
+        Input {
+            bindings: Bindings::default(),
+            gamepad: GamepadState::default(),
+            gilrs: None,
+            snapshot: ActionSnapshot::default(),
+        }
+    }
+
+    // One frame of `update` with the given gamepad state and no keyboard or mouse
+    fn frame(input: &mut Input, buttons: &[GamepadButton]) -> ActionSnapshot {
+        input.gamepad.buttons = buttons.iter().copied().collect();
+        input.sample(&Keyboard::default(), &Mouse::default());
+        input.gamepad.pressed.clear();
+        let snapshot = input.snapshot();
+        input.clear_presses();
+        snapshot
+    }
+
+    #[test]
+    fn gamepad_button_presses_holds_and_releases() {
+        let mut input = input();
+
+        let s = frame(&mut input, &[GamepadButton::South]);
+        assert!(s.pressed(Action::Jump) && s.held(Action::Jump) && !s.released(Action::Jump));
+
+        let s = frame(&mut input, &[GamepadButton::South]);
+        assert!(!s.pressed(Action::Jump) && s.held(Action::Jump) && !s.released(Action::Jump));
+
+        let s = frame(&mut input, &[]);
+        assert!(!s.pressed(Action::Jump) && !s.held(Action::Jump) && s.released(Action::Jump));
+
+        let s = frame(&mut input, &[]);
+        assert!(!s.pressed(Action::Jump) && !s.held(Action::Jump) && !s.released(Action::Jump));
+    }
+
+    #[test]
+    fn gamepad_press_shorter_than_a_frame_still_counts() {
+        let mut input = input();
+        input.gamepad.pressed.insert(GamepadButton::South);
+
+        let s = frame(&mut input, &[]);
+        assert!(s.pressed(Action::Jump));
+        assert!(!s.held(Action::Jump));
+    }
+
+    #[test]
+    fn keyboard_and_gamepad_share_an_action() {
+        let mut input = input();
+        let mut keyboard = Keyboard::default();
+        keyboard.down.insert(KeyCode::Space, 0.0);
+        input.sample(&keyboard, &Mouse::default());
+        input.clear_presses();
+
+        // Grabbing the pad while the key is still down isn't a new press
+        input.gamepad.buttons.insert(GamepadButton::South);
+        input.sample(&keyboard, &Mouse::default());
+        let s = input.snapshot();
+        assert!(s.held(Action::Jump) && !s.pressed(Action::Jump));
+    }
+
+    #[test]
+    fn gamepad_axis_dead_zone_and_axis_press() {
+        let mut input = input();
+        let mut stick = |value: f32| {
+            input.gamepad.axes.insert(GamepadAxis::LeftStickX, value);
+            frame(&mut input, &[])
+        };
+
+        let s = stick(DEAD_ZONE / 2.0);
+        assert_eq!(s.axis(Axis::MoveX), 0.0);
+        assert_eq!(s.axis_pressed(Axis::MoveX), 0.0);
+
+        let s = stick(0.8);
+        assert_eq!(s.axis(Axis::MoveX), 0.8);
+        assert_eq!(s.axis_pressed(Axis::MoveX), 1.0);
+
+        let s = stick(0.9);
+        assert_eq!(s.axis_pressed(Axis::MoveX), 0.0);
+
+        let s = stick(-0.9);
+        assert_eq!(s.axis_pressed(Axis::MoveX), -1.0);
+    }
+
+    #[test]
+    fn gamepad_bindings_round_trip_through_json() {
+        let mut bindings = Bindings::default();
+        bindings.actions.insert(
+            Action::Jump,
+            vec![Binding::GamepadButton(GamepadButton::East)],
+        );
+        bindings.axes.insert(
+            Axis::MoveX,
+            vec![AxisBinding::GamepadAxis(GamepadAxis::RightStickX)],
+        );
+
+        let json = serde_json::to_string(&bindings).unwrap();
+        let loaded: Bindings = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            loaded.actions[&Action::Jump],
+            [Binding::GamepadButton(GamepadButton::East)]
+        );
+        assert_eq!(
+            loaded.axes[&Axis::MoveX],
+            [AxisBinding::GamepadAxis(GamepadAxis::RightStickX)]
+        );
+    }
+}
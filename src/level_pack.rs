@@ -0,0 +1,59 @@
+use crate::soko::SokoLevel;
+
+/// A file of XSB levels separated by blank or text lines. A `Title:` line names the level it
+/// follows, or the next one when the previous level already has a title, so packs that put
+/// titles above their levels work too. Other text, like `Author:` lines and `;` comments, is skipped.
+pub struct LevelPack {
+    pub levels: Vec<SokoLevel>,
+}
+
+impl LevelPack {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut boards: Vec<(Option<String>, String)> = Vec::new();
+        let mut board = String::new();
+        let mut pending_title = None;
+
+        for line in text.lines() {
+            if is_board_line(line) {
+                board.push_str(line);
+                board.push('\n');
+                continue;
+            }
+
+            if !board.is_empty() {
+                boards.push((pending_title.take(), std::mem::take(&mut board)));
+            }
+
+            if let Some(title) = line.trim().strip_prefix("Title:") {
+                let title = title.trim().to_string();
+                match boards.last_mut() {
+                    Some((last_title @ None, _)) if pending_title.is_none() => {
+                        *last_title = Some(title)
+                    }
+                    _ => pending_title = Some(title),
+                }
+            }
+        }
+        if !board.is_empty() {
+            boards.push((pending_title.take(), board));
+        }
+
+        let levels = boards
+            .iter()
+            .enumerate()
+            .map(|(i, (title, board))| {
+                let title = title.clone().unwrap_or_else(|| format!("Level {}", i + 1));
+                SokoLevel::from_xsb(&title, board)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if levels.is_empty() {
+            return Err("level pack has no levels".to_string());
+        }
+        Ok(LevelPack { levels })
+    }
+}
+
+fn is_board_line(line: &str) -> bool {
+    line.contains('#') && line.chars().all(|c| "#@+$*.-_ ".contains(c))
+}
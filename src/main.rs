@@ -1,42 +1,62 @@
-use notan::math::Mat3;
 use notan::prelude::*;
 use notan::{draw::*, math::Vec2};
 
+mod aseprite;
+mod camera;
+mod combat;
+mod enemy;
 mod gun;
+#[cfg(test)]
+mod headless;
+mod input;
+mod level_pack;
+mod pathfinding;
 mod player;
+mod raycast;
 mod render;
+mod scene;
+mod scenes;
 mod soko;
+mod solver;
 mod textbox;
 mod tilemap;
 mod timer;
+mod timestep;
 mod top_down;
+mod trigger;
+mod validate;
 
-use player::Player;
+use input::{Bindings, Input};
 use render::PostProcessTarget;
-use textbox::{Conversation, Textbox};
-use tilemap::{TileMap, TileType, TILE_SIZE};
+use scene::{Assets, Context, SceneStack};
+use scenes::{DialogueScene, TopDownScene};
+use textbox::Message;
+use timestep::{FixedTimestep, FIXED_DT};
 
 const GAME_WIDTH: u32 = 320;
 const GAME_HEIGHT: u32 = 240;
 const WINDOW_WIDTH: u32 = GAME_WIDTH * 2;
 const WINDOW_HEIGHT: u32 = GAME_HEIGHT * 2;
+const BINDINGS_PATH: &str = "bindings.json";
 
 #[derive(AppState)]
 struct State {
-    soko_player: soko::SokoPlayer,
-    top_down: top_down::TopDownPlayer,
-    tilemap: TileMap,
-    jump_cooldown: f32,
+    assets: Assets,
+    scenes: SceneStack,
     post_process: PostProcessTarget,
-    conversation: Conversation,
-    gun: gun::Gun,
-    camera: Vec2,
-    camera_shake: Vec2,
-    shake_timer: f32,
+    timestep: FixedTimestep,
+    input: Input,
+    mouse: Vec2,
 }
 
 #[notan_main]
 fn main() -> Result<(), String> {
+    // `faba validate [pack.xsb...]` checks sokoban levels without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("validate") {
+        return validate::run(&args[1..]);
+    }
+
     let win_config = WindowConfig::new()
         .set_size(WINDOW_WIDTH, WINDOW_HEIGHT)
         .set_title("faba");
@@ -50,175 +70,61 @@ fn main() -> Result<(), String> {
 }
 
 fn setup(_app: &mut App, gfx: &mut Graphics) -> State {
-    let player = Player::new(50.0, 80.0);
-    let mut tilemap = TileMap::new(20, 15); // 320x240 screen with 16x16 tiles
-
-    // Set up an example level with slopes
-    for x in 0..20 {
-        tilemap.set_tile(x, 14, TileType::Solid); // Ground
-    }
-
-    // // Create a slope going up-right
-    // for x in 5..9 {
-    //     tilemap.set_tile(x, 13 - (x - 5), TileType::SlopeUpRight);
-    // }
-
-    // // Create a slope going up-left
-    // for x in 12..17 {
-    //     tilemap.set_tile(x, 9 + (x - 12), TileType::SlopeUpLeft);
-    // }
-
-    // Add some platforms
-    for x in 2..7 {
-        tilemap.set_tile(x, 10, TileType::Solid);
-    }
-    for x in 15..19 {
-        tilemap.set_tile(x, 5, TileType::Solid);
-    }
-
-    // // Fill the area under the slopes with solid tiles
-    // for x in 5..9 {
-    //     for y in (14 - (x - 5))..14 {
-    //         tilemap.set_tile(x, y, TileType::Solid);
-    //     }
-    // }
-    // for x in 12..17 {
-    //     for y in (10 + (x - 12))..14 {
-    //         tilemap.set_tile(x, y, TileType::Solid);
-    //     }
-    // }
-
+    let assets = Assets::load(gfx);
     let post_process = PostProcessTarget::new(gfx, GAME_WIDTH, GAME_HEIGHT);
-    let soko_player = soko::SokoPlayer::new(0, 0);
-
-    let top_down = top_down::TopDownPlayer::new(64., 64.);
 
-    let mut conversation = Conversation::new(vec![
-        textbox::Message::Text("This.".to_string()),
-        textbox::Message::Text("This is a test.".to_string()),
-        textbox::Message::Text("This is only a test.".to_string()),
-        textbox::Message::Text("This is a test of the emergency broadcast system.".to_string()),
-    ]);
-    conversation.setup(gfx);
+    let mut scenes = SceneStack::new(Box::new(TopDownScene::new(&assets).unwrap()));
+    scenes.push(Box::new(DialogueScene::new(
+        vec![
+            Message::Text("This.".to_string()),
+            Message::Text("This is a test.".to_string()),
+            Message::Text("This is only a test.".to_string()),
+            Message::Text("This is a test of the emergency broadcast system.".to_string()),
+        ],
+        &assets,
+    )));
+
+    // Write out the defaults on first run so there's a file to rebind controls in
+    let bindings = Bindings::load(BINDINGS_PATH).unwrap_or_else(|_| {
+        let bindings = Bindings::default();
+        let _ = bindings.save(BINDINGS_PATH);
+        bindings
+    });
 
     State {
-        soko_player,
-        top_down,
-        tilemap,
-        jump_cooldown: 0.0,
+        assets,
+        scenes,
         post_process,
-        conversation,
-        gun: gun::Gun::new(),
-        camera: Vec2::new(0.0, 0.0),
-        camera_shake: Vec2::new(0.0, 0.0),
-        shake_timer: 0.0,
+        timestep: FixedTimestep::new(FIXED_DT),
+        input: Input::new(bindings),
+        mouse: Vec2::ZERO,
     }
 }
 
 fn update(app: &mut App, state: &mut State) {
-    let dt = app.timer.delta_f32();
     let (mx, my) = app.mouse.position();
-
-    let left = app.keyboard.was_pressed(KeyCode::Left);
-    let right = app.keyboard.was_pressed(KeyCode::Right);
-    let up = app.keyboard.was_pressed(KeyCode::Up);
-    let down = app.keyboard.was_pressed(KeyCode::Down);
-
-    let shoot = app.mouse.was_pressed(MouseButton::Left);
-
-    let left_held = app.keyboard.is_down(KeyCode::A);
-    let right_held = app.keyboard.is_down(KeyCode::D);
-    let up_held = app.keyboard.is_down(KeyCode::W);
-    let down_held = app.keyboard.is_down(KeyCode::S);
-    let sprint = app.keyboard.is_down(KeyCode::LShift);
-    let advance = app.keyboard.was_pressed(KeyCode::Space);
-
-    if advance && state.conversation.textbox.finished_printing() {
-        state.conversation.advance();
-    }
-
-    state.soko_player.update(dt, left, right, up, down);
-    state
-        .top_down
-        .move_direction(up_held, down_held, left_held, right_held, sprint, dt);
-    state.top_down.update(&state.tilemap, dt);
-    state.gun.update(
-        (state.top_down.pos.x, state.top_down.pos.y),
-        ((mx / 2.) - state.camera.x, (my / 2.) - state.camera.y),
-        dt,
-    );
-    state.conversation.update(dt);
-
-    if shoot {
-        state.shake_timer = 0.1;
-        state.gun.shoot(state.top_down.pos.into());
-    }
-
-    if state.shake_timer > 0.0 {
-        state.shake_timer -= dt;
+    state.mouse = Vec2::new(mx / 2., my / 2.);
+    state.input.update(app);
+
+    for _ in 0..state.timestep.advance(app.timer.delta_f32()) {
+        let input = state.input.snapshot();
+        let ctx = Context {
+            input: &input,
+            mouse: state.mouse,
+            assets: &state.assets,
+        };
+        state.scenes.update(&ctx, FIXED_DT);
+        state.input.clear_presses();
     }
-
-    // Set camera to a random position in a small range
-    let shake_factor = if state.shake_timer > 0.0 { 6.0 } else { 0.0 };
-    let target_x = -state.top_down.pos.x + GAME_WIDTH as f32 / 2.0;
-    let target_y = -state.top_down.pos.y + GAME_HEIGHT as f32 / 2.0;
-    let factor = 1.0 / dt; // Adjust this value to control the easing speed
-    state.camera.x += (target_x - state.camera.x) / factor;
-    state.camera.y += (target_y - state.camera.y) / factor;
-    state.camera_shake.x = rand::random::<f32>() * shake_factor - shake_factor / 2.0;
-    state.camera_shake.y = rand::random::<f32>() * shake_factor - shake_factor / 2.0;
 }
 
 fn draw(app: &mut App, gfx: &mut Graphics, state: &mut State) {
     let mut draw = gfx.create_draw();
     draw.clear(Color::BLACK);
     draw.transform().clear();
-    draw.transform()
-        .push(Mat3::from_translation(state.camera + state.camera_shake));
-
-    // Draw tilemap
-    for y in 0..state.tilemap.height {
-        for x in 0..state.tilemap.width {
-            match state.tilemap.tiles[y][x] {
-                TileType::Solid => {
-                    draw.rect(
-                        (x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                        (TILE_SIZE, TILE_SIZE),
-                    )
-                    .color(Color::GRAY);
-                }
-                TileType::SlopeUpRight => {
-                    draw.triangle(
-                        (x as f32 * TILE_SIZE, (y + 1) as f32 * TILE_SIZE),
-                        ((x + 1) as f32 * TILE_SIZE, (y + 1) as f32 * TILE_SIZE),
-                        ((x + 1) as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                    )
-                    .color(Color::BLUE);
-                }
-                TileType::SlopeUpLeft => {
-                    draw.triangle(
-                        (x as f32 * TILE_SIZE, (y + 1) as f32 * TILE_SIZE),
-                        ((x + 1) as f32 * TILE_SIZE, (y + 1) as f32 * TILE_SIZE),
-                        (x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                    )
-                    .color(Color::GREEN);
-                }
-                TileType::Empty => {}
-            }
-        }
-    }
-
-    // // Draw player
-    state.soko_player.draw(&mut draw);
-    state.top_down.draw(&mut draw);
-
-    state.top_down.render_debug(&mut draw, &state.tilemap);
-    state.gun.draw(&mut draw, state.top_down.pos.into());
-
-    // Pop camera for UI
-    draw.transform().pop();
 
-    state.conversation.draw(&mut draw);
+    // Blend between the last two ticks so motion stays smooth at any refresh rate
+    state.scenes.draw(&mut draw, state.timestep.alpha());
 
     // Render the game scene to the post-process texture
     gfx.render_to(&state.post_process.render_texture, &draw);
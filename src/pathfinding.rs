@@ -0,0 +1,340 @@
+use crate::tilemap::{TileMap, TileType};
+use notan::math::{IVec2, Vec2};
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Step costs scaled by 10 so diagonals stay whole numbers
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+// Enemies chasing a moving target ask for a new goal most ticks, so the cache starts over
+// once it holds this many paths
+const CACHE_LIMIT: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PathOptions {
+    /// Allows the four diagonal steps as well as the straight ones
+    pub diagonal: bool,
+    /// Lets diagonal steps squeeze past a solid tile on one side, otherwise both tiles
+    /// next to the step have to be open
+    pub cut_corners: bool,
+    /// Width and height of the agent in tiles, every tile it covers has to be open
+    pub clearance: u32,
+    /// Drops waypoints that can be skipped by walking straight to a later one
+    pub smooth: bool,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        PathOptions {
+            diagonal: true,
+            cut_corners: false,
+            clearance: 1,
+            smooth: true,
+        }
+    }
+}
+
+type PathKey = (IVec2, IVec2, PathOptions);
+
+/// Paths found so far, keyed by start and goal tile. Cleared whenever `TileMap::set_tile`
+/// changes the map, or when it grows past `CACHE_LIMIT`.
+#[derive(Clone, Default)]
+pub struct PathCache {
+    paths: RefCell<HashMap<PathKey, Option<Vec<Vec2>>>>,
+}
+
+impl PathCache {
+    pub fn clear(&self) {
+        self.paths.borrow_mut().clear();
+    }
+
+    fn insert(&self, key: PathKey, path: Option<Vec<Vec2>>) {
+        let mut paths = self.paths.borrow_mut();
+        if paths.len() >= CACHE_LIMIT {
+            paths.clear();
+        }
+        paths.insert(key, path);
+    }
+}
+
+impl TileMap {
+    /// A* from the tile under `start` to the tile under `goal`, both in pixels. Returns the
+    /// waypoints to walk through in pixels, ending on the goal tile, or `None` when there is
+    /// no way there. Waypoints are where the center of an agent `clearance` tiles wide goes.
+    pub fn find_path(&self, start: Vec2, goal: Vec2, options: &PathOptions) -> Option<Vec<Vec2>> {
+        let start = self.tile_at(start, options.clearance);
+        let goal = self.tile_at(goal, options.clearance);
+        let key = (start, goal, *options);
+        if let Some(path) = self.path_cache.paths.borrow().get(&key) {
+            return path.clone();
+        }
+
+        let path = self.search(start, goal, options).map(|tiles| {
+            let tiles = if options.smooth {
+                self.smooth(&tiles, options.clearance)
+            } else {
+                tiles
+            };
+            tiles
+                .iter()
+                .map(|&tile| self.waypoint(tile, options.clearance))
+                .collect()
+        });
+        self.path_cache.insert(key, path.clone());
+        path
+    }
+
+    // Top-left tile of an agent centered on `pos`
+    fn tile_at(&self, pos: Vec2, clearance: u32) -> IVec2 {
+        let offset = (clearance as f32 - 1.0) / 2.0;
+        (pos / self.tile_size - Vec2::splat(offset))
+            .floor()
+            .as_ivec2()
+    }
+
+    fn waypoint(&self, tile: IVec2, clearance: u32) -> Vec2 {
+        (tile.as_vec2() + Vec2::splat(clearance as f32 / 2.0)) * self.tile_size
+    }
+
+    /// Whether an agent with its top-left corner on `tile` fits without touching a solid tile.
+    fn is_open(&self, tile: IVec2, clearance: u32) -> bool {
+        let size = clearance.max(1) as i32;
+        (0..size).all(|dy| {
+            (0..size).all(|dx| {
+                let (x, y) = (tile.x + dx, tile.y + dy);
+                x >= 0
+                    && y >= 0
+                    && (x as usize) < self.width
+                    && (y as usize) < self.height
+                    && self.tiles[y as usize][x as usize] != TileType::Solid
+            })
+        })
+    }
+
+    fn search(&self, start: IVec2, goal: IVec2, options: &PathOptions) -> Option<Vec<IVec2>> {
+        if !self.is_open(start, options.clearance) || !self.is_open(goal, options.clearance) {
+            return None;
+        }
+
+        let index = |tile: IVec2| tile.y as usize * self.width + tile.x as usize;
+        let mut cost = vec![u32::MAX; self.width * self.height];
+        let mut came_from = vec![None; self.width * self.height];
+        let mut open = BinaryHeap::new();
+        cost[index(start)] = 0;
+        open.push(Reverse((
+            heuristic(start, goal, options.diagonal),
+            start.x,
+            start.y,
+        )));
+
+        while let Some(Reverse((_, x, y))) = open.pop() {
+            let tile = IVec2::new(x, y);
+            if tile == goal {
+                let mut path = vec![goal];
+                let mut tile = goal;
+                while let Some(prev) = came_from[index(tile)] {
+                    path.push(prev);
+                    tile = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for (step, step_cost) in self.steps(tile, options) {
+                let next = tile + step;
+                let next_cost = cost[index(tile)] + step_cost;
+                if next_cost < cost[index(next)] {
+                    cost[index(next)] = next_cost;
+                    came_from[index(next)] = Some(tile);
+                    let estimate = next_cost + heuristic(next, goal, options.diagonal);
+                    open.push(Reverse((estimate, next.x, next.y)));
+                }
+            }
+        }
+        None
+    }
+
+    fn steps(&self, tile: IVec2, options: &PathOptions) -> Vec<(IVec2, u32)> {
+        let open = |step: IVec2| self.is_open(tile + step, options.clearance);
+        let mut steps: Vec<(IVec2, u32)> = [
+            IVec2::new(1, 0),
+            IVec2::new(-1, 0),
+            IVec2::new(0, 1),
+            IVec2::new(0, -1),
+        ]
+        .into_iter()
+        .filter(|&step| open(step))
+        .map(|step| (step, STRAIGHT_COST))
+        .collect();
+
+        if options.diagonal {
+            for step in [
+                IVec2::new(1, 1),
+                IVec2::new(-1, 1),
+                IVec2::new(1, -1),
+                IVec2::new(-1, -1),
+            ] {
+                let sides = [open(IVec2::new(step.x, 0)), open(IVec2::new(0, step.y))];
+                let corner_ok = if options.cut_corners {
+                    sides.iter().any(|&side| side)
+                } else {
+                    sides.iter().all(|&side| side)
+                };
+                if corner_ok && open(step) {
+                    steps.push((step, DIAGONAL_COST));
+                }
+            }
+        }
+        steps
+    }
+
+    // Keeps a waypoint only when the previous kept one can't walk straight past it
+    fn smooth(&self, tiles: &[IVec2], clearance: u32) -> Vec<IVec2> {
+        let mut smoothed = vec![tiles[0]];
+        let mut i = 0;
+        while i < tiles.len() - 1 {
+            let mut furthest = i + 1;
+            for j in (i + 2..tiles.len()).rev() {
+                if self.is_straight_walk(tiles[i], tiles[j], clearance) {
+                    furthest = j;
+                    break;
+                }
+            }
+            smoothed.push(tiles[furthest]);
+            i = furthest;
+        }
+        smoothed
+    }
+
+    // Samples the line between two tiles a quarter tile at a time, checking every tile an agent
+    // on it would overlap
+    fn is_straight_walk(&self, from: IVec2, to: IVec2, clearance: u32) -> bool {
+        let (from, to) = (from.as_vec2(), to.as_vec2());
+        let steps = (from.distance(to) * 4.0).ceil().max(1.0) as u32;
+        (0..=steps).all(|i| {
+            let point = from.lerp(to, i as f32 / steps as f32);
+            let corners = [
+                point.floor(),
+                Vec2::new(point.x.ceil(), point.y.floor()),
+                Vec2::new(point.x.floor(), point.y.ceil()),
+                point.ceil(),
+            ];
+            corners
+                .iter()
+                .all(|corner| self.is_open(corner.as_ivec2(), clearance))
+        })
+    }
+}
+
+fn heuristic(from: IVec2, to: IVec2, diagonal: bool) -> u32 {
+    let distance = (to - from).abs();
+    let (dx, dy) = (distance.x as u32, distance.y as u32);
+    if diagonal {
+        // Octile distance, diagonal steps for the shorter axis and straight ones for the rest
+        DIAGONAL_COST * dx.min(dy) + STRAIGHT_COST * dx.abs_diff(dy)
+    } else {
+        STRAIGHT_COST * (dx + dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Center of a tile in pixels
+    fn at(x: i32, y: i32) -> Vec2 {
+        (Vec2::new(x as f32, y as f32) + 0.5) * 16.0
+    }
+
+    fn walls(width: usize, height: usize, solid: &[(usize, usize)]) -> TileMap {
+        let mut map = TileMap::new(width, height);
+        for &(x, y) in solid {
+            map.set_tile(x, y, TileType::Solid);
+        }
+        map
+    }
+
+    fn unsmoothed(diagonal: bool, cut_corners: bool) -> PathOptions {
+        PathOptions {
+            diagonal,
+            cut_corners,
+            smooth: false,
+            ..PathOptions::default()
+        }
+    }
+
+    #[test]
+    fn diagonal_steps_shorten_the_path() {
+        let map = TileMap::new(5, 5);
+        let straight = map.find_path(at(0, 0), at(4, 4), &unsmoothed(false, false));
+        let diagonal = map.find_path(at(0, 0), at(4, 4), &unsmoothed(true, false));
+        assert_eq!(straight.unwrap().len(), 9);
+        assert_eq!(
+            diagonal.unwrap(),
+            (0..5).map(|i| at(i, i)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn diagonals_only_cut_corners_when_allowed() {
+        let map = walls(2, 2, &[(1, 0)]);
+        let path = map.find_path(at(0, 0), at(1, 1), &unsmoothed(true, false));
+        assert_eq!(path.unwrap(), [at(0, 0), at(0, 1), at(1, 1)]);
+        let path = map.find_path(at(0, 0), at(1, 1), &unsmoothed(true, true));
+        assert_eq!(path.unwrap(), [at(0, 0), at(1, 1)]);
+
+        // Never between two solid corners
+        let map = walls(2, 2, &[(1, 0), (0, 1)]);
+        assert_eq!(
+            map.find_path(at(0, 0), at(1, 1), &unsmoothed(true, false)),
+            None
+        );
+        assert_eq!(
+            map.find_path(at(0, 0), at(1, 1), &unsmoothed(true, true)),
+            None
+        );
+    }
+
+    #[test]
+    fn wide_agents_dont_fit_through_narrow_gaps() {
+        // A wall down the middle with a gap one tile high
+        let map = walls(6, 6, &[(3, 0), (3, 1), (3, 3), (3, 4), (3, 5)]);
+        let small = PathOptions::default();
+        let wide = PathOptions {
+            clearance: 2,
+            ..PathOptions::default()
+        };
+        assert!(map.find_path(at(0, 2), at(5, 2), &small).is_some());
+        assert_eq!(map.find_path(at(1, 2), at(5, 2), &wide), None);
+    }
+
+    #[test]
+    fn smoothing_drops_waypoints_on_a_straight_line() {
+        let map = TileMap::new(5, 1);
+        let path = map.find_path(at(0, 0), at(4, 0), &unsmoothed(true, false));
+        assert_eq!(path.unwrap().len(), 5);
+        let path = map.find_path(at(0, 0), at(4, 0), &PathOptions::default());
+        assert_eq!(path.unwrap(), [at(0, 0), at(4, 0)]);
+    }
+
+    #[test]
+    fn changing_a_tile_clears_cached_paths() {
+        let mut map = TileMap::new(3, 1);
+        let options = PathOptions::default();
+        assert!(map.find_path(at(0, 0), at(2, 0), &options).is_some());
+
+        map.set_tile(1, 0, TileType::Solid);
+        assert_eq!(map.find_path(at(0, 0), at(2, 0), &options), None);
+    }
+
+    #[test]
+    fn cache_starts_over_when_full() {
+        let map = TileMap::new(CACHE_LIMIT + 1, 1);
+        let options = PathOptions::default();
+        for x in 0..=CACHE_LIMIT as i32 {
+            map.find_path(at(0, 0), at(x, 0), &options);
+        }
+        assert_eq!(map.path_cache.paths.borrow().len(), 1);
+    }
+}
@@ -1,51 +1,109 @@
-use crate::tilemap::{TileMap, TileType, TILE_SIZE};
+use crate::aseprite::{Animation, SpriteSheet};
+use crate::tilemap::{TileMap, TileType};
+use crate::timer::Timer;
 use notan::draw::*;
 use notan::math::Vec2;
 use notan::prelude::*;
 
+/// The platformer's player. It has no `Health`, nothing in the platformer deals damage yet,
+/// unlike `TopDownPlayer` which gets shot at.
 pub struct Player {
     pub pos: Vec2,
+    pub prev_pos: Vec2,
     pub velocity: Vec2,
     pub temp_velocity: Vec2,
     pub size: Vec2,
     pub on_ground: bool,
+    pub on_one_way: bool,
+    pub on_wall_left: bool,
+    pub on_wall_right: bool,
+    pub wall_sliding: bool,
     pub acceleration: Vec2,
     pub friction: Vec2,
     pub max_speed: Vec2,
     pub moved_amount: Vec2,
+    /// Grace period after walking off a ledge during which a jump still works
+    pub coyote_timer: Timer,
+    /// How long a jump pressed in the air is remembered, so it fires on landing
+    pub jump_buffer: Timer,
+    pub sprite: Animation,
+    pub facing_left: bool,
     collision_types: Vec<TileType>,
     jump_timer: f32,
     max_jump_time: f32,
     jump_force: f32,
+    wall_slide_speed: f32,
+    wall_jump_kick: f32,
+    // Horizontal input is ignored for a moment after a wall jump so it carries the player away
+    wall_jump_lock: Timer,
+    move_input: f32,
     sprint_speed_multiplier: f32,
+    // Top of the one-way platform being dropped through, ignored until the player is below it
+    drop_through_y: Option<f32>,
 }
 
 impl Player {
-    pub fn new(x: f32, y: f32) -> Self {
+    pub fn new(x: f32, y: f32, sheet: &SpriteSheet) -> Self {
         Player {
             pos: Vec2::new(x, y),
+            prev_pos: Vec2::new(x, y),
             velocity: Vec2::ZERO,
             temp_velocity: Vec2::ZERO,
             size: Vec2::new(8.0, 8.0),
             on_ground: false,
+            on_one_way: false,
+            on_wall_left: false,
+            on_wall_right: false,
+            wall_sliding: false,
             acceleration: Vec2::new(0.0, 0.01),
             friction: Vec2::new(1.15, 1.0),
             max_speed: Vec2::new(2., 10.0),
             moved_amount: Vec2::ZERO,
+            coyote_timer: finished_timer(0.1),
+            jump_buffer: finished_timer(0.1),
+            sprite: Animation::new(sheet),
+            facing_left: false,
             collision_types: vec![
                 TileType::Solid,
                 TileType::SlopeUpRight,
                 TileType::SlopeUpLeft,
+                TileType::OneWay,
             ],
             jump_timer: 0.0,
             max_jump_time: 0.1, // Maximum time the jump button can be held for higher jumps
             jump_force: -5.0,   // Initial jump force
+            wall_slide_speed: 0.5, // Maximum fall speed while sliding down a wall
+            wall_jump_kick: 3.0, // Horizontal speed away from the wall on a wall jump
+            wall_jump_lock: finished_timer(0.15),
+            move_input: 0.0,
             sprint_speed_multiplier: 2., // Speed multiplier when sprinting
+            drop_through_y: None,
         }
     }
 
     pub fn update(&mut self, tilemap: &TileMap, dt: f32, jump_button_held: bool) {
+        self.prev_pos = self.pos;
         self.collision_bottom(tilemap);
+        self.collision_sides(tilemap);
+        self.wall_jump_lock.update(dt);
+        self.sprite.update(dt);
+        let tag = if self.on_ground && self.velocity.x.abs() > 0.1 {
+            "walk"
+        } else {
+            "idle"
+        };
+        // Sheets without the tag keep playing whatever they were
+        let _ = self.sprite.play(tag);
+
+        if self.on_ground {
+            self.coyote_timer.reset();
+            if !self.jump_buffer.is_finished() {
+                self.start_jump();
+            }
+        } else {
+            self.coyote_timer.update(dt);
+        }
+        self.jump_buffer.update(dt);
 
         // Handle variable height jumping
         if jump_button_held && self.jump_timer > 0.0 {
@@ -62,6 +120,14 @@ impl Player {
             self.velocity.y += self.acceleration.y;
         }
 
+        self.wall_sliding = !self.on_ground
+            && self.velocity.y > 0.0
+            && ((self.on_wall_left && self.move_input < 0.0)
+                || (self.on_wall_right && self.move_input > 0.0));
+        if self.wall_sliding {
+            self.velocity.y = self.velocity.y.min(self.wall_slide_speed);
+        }
+
         self.velocity.x /= self.friction.x;
         if !self.on_ground {
             self.velocity.y /= self.friction.y;
@@ -69,6 +135,12 @@ impl Player {
 
         self.move_object(tilemap, dt);
         self.clamp_speed();
+
+        if let Some(platform_y) = self.drop_through_y {
+            if self.pos.y + self.size.y - 1.0 > platform_y {
+                self.drop_through_y = None;
+            }
+        }
     }
 
     fn move_object(&mut self, tilemap: &TileMap, dt: f32) {
@@ -117,11 +189,13 @@ impl Player {
         self.moved_amount.y += 1.0;
         let new_y = self.pos.y + dir as f32;
 
-        if !self.collide(tilemap, self.pos.x, new_y) {
+        if self.collide(tilemap, self.pos.x, new_y) {
+            if dir > 0 {
+                // Moving down, check for slopes
+                self.try_step_down_slope(tilemap);
+            }
+        } else if dir <= 0 || !self.collide_one_way(tilemap, self.pos.x, new_y) {
             self.pos.y = new_y;
-        } else if dir > 0 {
-            // Moving down, check for slopes
-            self.try_step_down_slope(tilemap);
         }
 
         if self.collide(tilemap, self.pos.x, self.pos.y - 1.0) {
@@ -130,7 +204,7 @@ impl Player {
     }
 
     fn try_step_up_slope(&mut self, tilemap: &TileMap, dir: i32) -> bool {
-        for step in 1..=((TILE_SIZE / 2.0) as i32) {
+        for step in 1..=((tilemap.tile_size / 2.0) as i32) {
             if !self.collide(tilemap, self.pos.x + dir as f32, self.pos.y - step as f32) {
                 self.pos.x += dir as f32;
                 self.pos.y -= step as f32;
@@ -142,7 +216,7 @@ impl Player {
 
     fn try_step_down_slope(&mut self, tilemap: &TileMap) {
         let mut step = 1;
-        while step <= (TILE_SIZE as i32)
+        while step <= (tilemap.tile_size as i32)
             && !self.collide(tilemap, self.pos.x, self.pos.y + step as f32)
         {
             step += 1;
@@ -157,6 +231,7 @@ impl Player {
         let right = x + self.size.x - 1.0;
         let top = y;
         let bottom = y + self.size.y - 1.0;
+        let tile_size = tilemap.tile_size;
 
         for check_y in [top, bottom] {
             for check_x in [left, right] {
@@ -164,16 +239,16 @@ impl Player {
                 match tile_type {
                     TileType::Solid => return true,
                     TileType::SlopeUpRight => {
-                        let tile_x = (check_x / TILE_SIZE).floor() * TILE_SIZE;
-                        let tile_y = (check_y / TILE_SIZE).floor() * TILE_SIZE;
-                        let slope_y = TILE_SIZE - (check_x - tile_x);
+                        let tile_x = (check_x / tile_size).floor() * tile_size;
+                        let tile_y = (check_y / tile_size).floor() * tile_size;
+                        let slope_y = tile_size - (check_x - tile_x);
                         if check_y >= tile_y + slope_y {
                             return true;
                         }
                     }
                     TileType::SlopeUpLeft => {
-                        let tile_x = (check_x / TILE_SIZE).floor() * TILE_SIZE;
-                        let tile_y = (check_y / TILE_SIZE).floor() * TILE_SIZE;
+                        let tile_x = (check_x / tile_size).floor() * tile_size;
+                        let tile_y = (check_y / tile_size).floor() * tile_size;
                         let slope_y = check_x - tile_x;
                         if check_y >= tile_y + slope_y {
                             return true;
@@ -186,16 +261,50 @@ impl Player {
         false
     }
 
+    /// One-way tiles only stop the player's feet, on the top edge of the tile.
+    /// Callers only check this while moving down, so they can be jumped through from below.
+    fn collide_one_way(&self, tilemap: &TileMap, x: f32, y: f32) -> bool {
+        let bottom = y + self.size.y - 1.0;
+        let tile_top = (bottom / tilemap.tile_size).floor() * tilemap.tile_size;
+        if bottom - tile_top >= 1.0 || self.drop_through_y == Some(tile_top) {
+            return false;
+        }
+
+        [x, x + self.size.x - 1.0]
+            .iter()
+            .any(|&check_x| tilemap.is_tile_type(check_x, bottom, TileType::OneWay))
+    }
+
     fn collision_bottom(&mut self, tilemap: &TileMap) {
         self.on_ground = false;
+        self.on_one_way = false;
+        // The ground is still underfoot on the tick a jump starts, counting it would reopen the
+        // coyote window and let the player jump again in mid-air
+        if self.velocity.y < 0.0 {
+            return;
+        }
         let feet_y = self.pos.y + self.size.y;
+        self.on_one_way = self.collide_one_way(tilemap, self.pos.x, self.pos.y + 1.0);
         if self.collide(tilemap, self.pos.x, feet_y)
             || self.collide(tilemap, self.pos.x + self.size.x - 1.0, feet_y)
+            || self.on_one_way
         {
             self.on_ground = true;
         }
     }
 
+    fn collision_sides(&mut self, tilemap: &TileMap) {
+        self.on_wall_left = self.wall_at(tilemap, self.pos.x - 1.0);
+        self.on_wall_right = self.wall_at(tilemap, self.pos.x + self.size.x);
+    }
+
+    // Only solid tiles count as walls, slopes are walked up instead
+    fn wall_at(&self, tilemap: &TileMap, x: f32) -> bool {
+        [self.pos.y, self.pos.y + self.size.y - 1.0]
+            .iter()
+            .any(|&y| tilemap.is_tile_type(x, y, TileType::Solid))
+    }
+
     fn hit_wall(&mut self) {
         self.temp_velocity.x = 0.0;
         self.velocity.x = 0.0;
@@ -212,6 +321,17 @@ impl Player {
     }
 
     pub fn move_horizontal(&mut self, left: bool, right: bool, sprint: bool, dt: f32) {
+        self.move_input = if left {
+            -1.0
+        } else if right {
+            1.0
+        } else {
+            0.0
+        };
+        if !self.wall_jump_lock.is_finished() {
+            return;
+        }
+
         let base_acceleration = 420.0;
         let acceleration = if sprint {
             base_acceleration * self.sprint_speed_multiplier
@@ -221,19 +341,41 @@ impl Player {
 
         if left {
             self.velocity.x -= acceleration * dt;
+            self.facing_left = true;
         } else if right {
             self.velocity.x += acceleration * dt;
+            self.facing_left = false;
         }
     }
 
-    pub fn jump(&mut self) {
-        if self.on_ground {
-            self.velocity.y = self.jump_force;
-            self.jump_timer = self.max_jump_time;
+    /// Jumps when on the ground or shortly after leaving it, kicks off a wall when touching one,
+    /// otherwise the jump is buffered until landing. Holding down while standing on a one-way
+    /// platform drops through it instead.
+    pub fn jump(&mut self, down_held: bool) {
+        if down_held && self.on_one_way {
+            self.drop_through_y = Some(self.pos.y + self.size.y);
             self.on_ground = false;
+            self.on_one_way = false;
+        } else if self.on_ground || !self.coyote_timer.is_finished() {
+            self.start_jump();
+        } else if self.on_wall_left || self.on_wall_right {
+            let away = if self.on_wall_left { 1.0 } else { -1.0 };
+            self.start_jump();
+            self.velocity.x = away * self.wall_jump_kick;
+            self.wall_jump_lock.reset();
+        } else {
+            self.jump_buffer.reset();
         }
     }
 
+    fn start_jump(&mut self) {
+        self.velocity.y = self.jump_force;
+        self.jump_timer = self.max_jump_time;
+        self.on_ground = false;
+        self.coyote_timer.finish();
+        self.jump_buffer.finish();
+    }
+
     pub fn cancel_jump(&mut self) {
         if self.velocity.y < 0.0 {
             self.velocity.y *= 0.5; // Reduce upward velocity when jump is cancelled
@@ -241,6 +383,16 @@ impl Player {
         self.jump_timer = 0.0;
     }
 
+    /// Position to draw at, `alpha` blends from the previous tick to the current one.
+    pub fn render_pos(&self, alpha: f32) -> Vec2 {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
+
+    pub fn draw(&self, draw: &mut Draw, alpha: f32) {
+        let feet = self.render_pos(alpha) + Vec2::new(self.size.x / 2.0, self.size.y);
+        self.sprite.draw(draw, feet, self.facing_left, 1.0);
+    }
+
     pub fn render_debug(&self, draw: &mut Draw, tilemap: &TileMap) {
         // Render player bounding box
         draw.rect((self.pos.x, self.pos.y), (self.size.x, self.size.y))
@@ -289,34 +441,42 @@ impl Player {
         .color(Color::BLUE);
 
         // Render tiles that the player is colliding with
+        let tile_size = tilemap.tile_size;
         for y in (self.pos.y as i32 - 1..=(self.pos.y + self.size.y) as i32 + 1) {
             for x in (self.pos.x as i32 - 1..=(self.pos.x + self.size.x) as i32 + 1) {
                 let tile_type = tilemap.get_tile_type(x as f32, y as f32);
                 match tile_type {
                     TileType::Solid => {
                         draw.rect(
-                            (x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                            (TILE_SIZE, TILE_SIZE),
+                            (x as f32 * tile_size, y as f32 * tile_size),
+                            (tile_size, tile_size),
                         )
                         .stroke_color(Color::BLUE);
                     }
+                    TileType::OneWay => {
+                        draw.line(
+                            (x as f32 * tile_size, y as f32 * tile_size),
+                            ((x + 1) as f32 * tile_size, y as f32 * tile_size),
+                        )
+                        .color(Color::ORANGE);
+                    }
                     TileType::SlopeUpRight | TileType::SlopeUpLeft => {
                         draw.rect(
-                            (x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                            (TILE_SIZE, TILE_SIZE),
+                            (x as f32 * tile_size, y as f32 * tile_size),
+                            (tile_size, tile_size),
                         )
                         .stroke_color(Color::YELLOW);
 
                         // Draw slope line
                         let (start, end) = if tile_type == TileType::SlopeUpRight {
                             (
-                                (x as f32 * TILE_SIZE, (y + 1) as f32 * TILE_SIZE),
-                                ((x + 1) as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
+                                (x as f32 * tile_size, (y + 1) as f32 * tile_size),
+                                ((x + 1) as f32 * tile_size, y as f32 * tile_size),
                             )
                         } else {
                             (
-                                (x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                                ((x + 1) as f32 * TILE_SIZE, (y + 1) as f32 * TILE_SIZE),
+                                (x as f32 * tile_size, y as f32 * tile_size),
+                                ((x + 1) as f32 * tile_size, (y + 1) as f32 * tile_size),
                             )
                         };
                         draw.line((start.0, start.1), (end.0, end.1))
@@ -328,3 +488,152 @@ impl Player {
         }
     }
 }
+
+fn finished_timer(duration: f32) -> Timer {
+    let mut timer = Timer::new(duration);
+    timer.finish();
+    timer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    // A floor along the bottom row of a small room, with the player standing on it
+    fn setup() -> (TileMap, Player) {
+        let mut tilemap = TileMap::new(20, 10);
+        for x in 0..20 {
+            tilemap.set_tile(x, 9, TileType::Solid);
+        }
+        let floor = 9.0 * tilemap.tile_size;
+        let mut player = Player::new(40.0, floor - 8.0, &headless::skeletron());
+        step(&tilemap, &mut player, 2);
+        assert!(player.on_ground);
+        (tilemap, player)
+    }
+
+    fn step(tilemap: &TileMap, player: &mut Player, ticks: u32) {
+        for _ in 0..ticks {
+            player.update(tilemap, DT, false);
+        }
+    }
+
+    // Walks the player off the floor by taking the tiles out from under them
+    fn remove_floor(tilemap: &mut TileMap) {
+        for x in 0..20 {
+            tilemap.set_tile(x, 9, TileType::Empty);
+        }
+    }
+
+    fn rising(player: &Player) -> bool {
+        player.velocity.y < 0.0
+    }
+
+    // Drops the player from `height` pixels above the floor and lets them fall for longer than
+    // the coyote window
+    fn drop(tilemap: &TileMap, player: &mut Player, height: f32) {
+        player.pos.y -= height;
+        step(tilemap, player, 10);
+        assert!(!player.on_ground && player.coyote_timer.is_finished());
+    }
+
+    fn floor_distance(tilemap: &TileMap, player: &Player) -> f32 {
+        9.0 * tilemap.tile_size - (player.pos.y + player.size.y)
+    }
+
+    #[test]
+    fn jumps_from_the_ground() {
+        let (tilemap, mut player) = setup();
+        player.jump(false);
+        assert!(rising(&player));
+        step(&tilemap, &mut player, 1);
+        assert!(!player.on_ground);
+        assert!(floor_distance(&tilemap, &player) > 0.0);
+    }
+
+    #[test]
+    fn jumps_inside_the_coyote_window() {
+        let (mut tilemap, mut player) = setup();
+        remove_floor(&mut tilemap);
+        step(&tilemap, &mut player, 3);
+        assert!(!player.on_ground && !rising(&player));
+
+        player.jump(false);
+        assert!(rising(&player));
+    }
+
+    #[test]
+    fn no_jump_after_the_coyote_window() {
+        let (mut tilemap, mut player) = setup();
+        remove_floor(&mut tilemap);
+        step(&tilemap, &mut player, 10);
+
+        player.jump(false);
+        assert!(!rising(&player));
+    }
+
+    #[test]
+    fn buffered_jump_fires_on_landing() {
+        let (tilemap, mut player) = setup();
+        drop(&tilemap, &mut player, 40.0);
+        // The player counts as grounded within their own height of the floor
+        while floor_distance(&tilemap, &player) > player.size.y + 2.0 {
+            step(&tilemap, &mut player, 1);
+        }
+
+        // Pressed just before touching down, too early to jump
+        player.jump(false);
+        assert!(!rising(&player));
+
+        let mut jumped = false;
+        for _ in 0..5 {
+            step(&tilemap, &mut player, 1);
+            jumped |= rising(&player);
+        }
+        assert!(jumped);
+    }
+
+    #[test]
+    fn buffered_jump_expires() {
+        let (tilemap, mut player) = setup();
+        drop(&tilemap, &mut player, 40.0);
+
+        // Pressed far too early, the buffer runs out before landing
+        player.jump(false);
+        for _ in 0..600 {
+            step(&tilemap, &mut player, 1);
+            assert!(!rising(&player));
+        }
+        assert!(player.on_ground);
+    }
+
+    #[test]
+    fn no_double_jump_after_a_normal_jump() {
+        let (tilemap, mut player) = setup();
+        player.jump(false);
+        step(&tilemap, &mut player, 1);
+        player.cancel_jump();
+        step(&tilemap, &mut player, 1);
+
+        // Still well inside what would have been the coyote window
+        let velocity = player.velocity.y;
+        player.jump(false);
+        assert_eq!(player.velocity.y, velocity);
+    }
+
+    #[test]
+    fn render_pos_blends_between_ticks() {
+        let (tilemap, mut player) = setup();
+        let start = player.pos;
+        player.jump(false);
+        step(&tilemap, &mut player, 1);
+        assert_ne!(player.pos, start);
+
+        assert_eq!(player.render_pos(0.0), start);
+        assert_eq!(player.render_pos(1.0), player.pos);
+        assert_eq!(player.render_pos(0.5), start.lerp(player.pos, 0.5));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::tilemap::{TileMap, TileType};
+use notan::math::{IVec2, Vec2};
+
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub point: Vec2,
+    pub tile: IVec2,
+    /// Points out of the surface that was hit
+    pub normal: Vec2,
+    /// From the ray's origin to `point`
+    pub distance: f32,
+}
+
+impl TileMap {
+    /// Walks the tiles along a ray (DDA) and returns the first solid surface within `max_dist`
+    /// pixels. Slopes are hit on their diagonal face, one-way tiles are passed through like
+    /// everything else that isn't solid. A ray starting inside something solid, including the
+    /// solid half of a slope, hits it at distance zero.
+    pub fn raycast(&self, origin: Vec2, dir: Vec2, max_dist: f32) -> Option<RayHit> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec2::ZERO {
+            return None;
+        }
+
+        let mut tile = (origin / self.tile_size).floor().as_ivec2();
+        let step = IVec2::new(dir.x.signum() as i32, dir.y.signum() as i32);
+        // Distance along the ray to cross a whole tile on each axis, infinite along an axis the
+        // ray doesn't move on
+        let delta = Vec2::new(self.tile_size / dir.x.abs(), self.tile_size / dir.y.abs());
+        let boundary = |origin: f32, dir: f32, tile: i32| {
+            if dir == 0.0 {
+                return f32::INFINITY;
+            }
+            let edge = if dir > 0.0 { tile + 1 } else { tile } as f32 * self.tile_size;
+            (edge - origin) / dir
+        };
+        let mut next = Vec2::new(
+            boundary(origin.x, dir.x, tile.x),
+            boundary(origin.y, dir.y, tile.y),
+        );
+
+        let mut distance = 0.0;
+        // The face the ray came in through, none for the tile it starts in
+        let mut entry_normal = Vec2::ZERO;
+        while distance <= max_dist {
+            let exit = next.x.min(next.y);
+            if let Some(hit) = self.hit_in_tile(tile, origin, dir, distance, exit, entry_normal) {
+                return Some(hit).filter(|hit| hit.distance <= max_dist);
+            }
+
+            if next.x < next.y {
+                distance = next.x;
+                next.x += delta.x;
+                tile.x += step.x;
+                entry_normal = Vec2::new(-step.x as f32, 0.0);
+            } else {
+                distance = next.y;
+                next.y += delta.y;
+                tile.y += step.y;
+                entry_normal = Vec2::new(0.0, -step.y as f32);
+            }
+        }
+        None
+    }
+
+    /// Whether nothing solid is in the way between two points.
+    pub fn has_line_of_sight(&self, a: Vec2, b: Vec2) -> bool {
+        self.raycast(a, b - a, a.distance(b)).is_none()
+    }
+
+    // Where the ray hits inside one tile, given the distances it enters and leaves the tile at
+    fn hit_in_tile(
+        &self,
+        tile: IVec2,
+        origin: Vec2,
+        dir: Vec2,
+        entry: f32,
+        exit: f32,
+        entry_normal: Vec2,
+    ) -> Option<RayHit> {
+        let hit = |distance: f32, normal: Vec2| RayHit {
+            point: origin + dir * distance,
+            tile,
+            normal,
+            distance,
+        };
+        let in_bounds = tile.x >= 0
+            && tile.y >= 0
+            && (tile.x as usize) < self.width
+            && (tile.y as usize) < self.height;
+        // Out of bounds counts as solid, same as `get_tile_type`
+        let tile_type = if in_bounds {
+            self.tiles[tile.y as usize][tile.x as usize]
+        } else {
+            TileType::Solid
+        };
+
+        // The diagonal face's normal and a corner the diagonal runs through
+        let (normal, corner) = match tile_type {
+            TileType::Solid if entry_normal == Vec2::ZERO => return Some(hit(0.0, -dir)),
+            TileType::Solid => return Some(hit(entry, entry_normal)),
+            TileType::SlopeUpRight => (Vec2::new(-1.0, -1.0).normalize(), IVec2::new(1, 0)),
+            TileType::SlopeUpLeft => (Vec2::new(1.0, -1.0).normalize(), IVec2::new(0, 0)),
+            TileType::OneWay | TileType::Empty => return None,
+        };
+
+        // Coming in through one of the slope's flat sides, which are solid all the way along
+        if entry_normal.dot(normal) < 0.0 {
+            return Some(hit(entry, entry_normal));
+        }
+        let corner = (tile + corner).as_vec2() * self.tile_size;
+        // Starting below the diagonal, already inside the slope
+        if entry_normal == Vec2::ZERO && normal.dot(origin - corner) < 0.0 {
+            return Some(hit(0.0, -dir));
+        }
+        if dir.dot(normal) >= 0.0 {
+            return None;
+        }
+        let distance = normal.dot(corner - origin) / normal.dot(dir);
+        (distance >= entry && distance <= exit).then(|| hit(distance, normal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(width: usize, height: usize, tiles: &[(usize, usize, TileType)]) -> TileMap {
+        let mut map = TileMap::new(width, height);
+        for &(x, y, tile_type) in tiles {
+            map.set_tile(x, y, tile_type);
+        }
+        map
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn hits_the_near_face_of_a_wall() {
+        let map = map(5, 3, &[(3, 1, TileType::Solid)]);
+        let hit = map.raycast(Vec2::new(8.0, 24.0), Vec2::X, 100.0).unwrap();
+        assert_eq!(hit.tile, IVec2::new(3, 1));
+        assert_eq!(hit.normal, Vec2::NEG_X);
+        assert_eq!(hit.distance, 40.0);
+        assert_eq!(hit.point, Vec2::new(48.0, 24.0));
+    }
+
+    #[test]
+    fn hits_the_diagonal_face_of_a_slope() {
+        let map = map(5, 5, &[(2, 2, TileType::SlopeUpRight)]);
+        let hit = map.raycast(Vec2::new(8.0, 4.0), Vec2::ONE, 100.0).unwrap();
+        assert_eq!(hit.tile, IVec2::new(2, 2));
+        assert_near(hit.normal, Vec2::new(-1.0, -1.0).normalize());
+        assert_near(hit.point, Vec2::new(42.0, 38.0));
+        assert!((hit.distance - 34.0 * 2f32.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn stops_looking_past_max_dist() {
+        let map = map(5, 3, &[(3, 1, TileType::Solid)]);
+        assert!(map.raycast(Vec2::new(8.0, 24.0), Vec2::X, 30.0).is_none());
+        assert!(map.raycast(Vec2::new(8.0, 24.0), Vec2::X, 50.0).is_some());
+    }
+
+    #[test]
+    fn out_of_bounds_is_solid() {
+        let map = map(3, 3, &[]);
+        let hit = map
+            .raycast(Vec2::new(24.0, 24.0), Vec2::NEG_X, 100.0)
+            .unwrap();
+        assert_eq!(hit.tile, IVec2::new(-1, 1));
+        assert_eq!(hit.normal, Vec2::X);
+        assert_eq!(hit.distance, 24.0);
+    }
+
+    #[test]
+    fn starting_inside_a_slope_hits_at_once() {
+        let map = map(3, 3, &[(1, 1, TileType::SlopeUpRight)]);
+        // Below the diagonal, heading out through it
+        let hit = map
+            .raycast(Vec2::new(30.0, 30.0), Vec2::NEG_ONE, 100.0)
+            .unwrap();
+        assert_eq!(hit.distance, 0.0);
+        assert_eq!(hit.point, Vec2::new(30.0, 30.0));
+
+        // Above it, the diagonal is still in the way
+        let hit = map
+            .raycast(Vec2::new(18.0, 18.0), Vec2::ONE, 100.0)
+            .unwrap();
+        assert!((hit.distance - 6.0 * 2f32.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn walls_block_line_of_sight() {
+        let map = map(5, 3, &[(2, 1, TileType::Solid)]);
+        assert!(!map.has_line_of_sight(Vec2::new(8.0, 24.0), Vec2::new(72.0, 24.0)));
+        assert!(map.has_line_of_sight(Vec2::new(8.0, 8.0), Vec2::new(72.0, 8.0)));
+    }
+}
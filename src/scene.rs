@@ -0,0 +1,188 @@
+use crate::aseprite::SpriteSheet;
+use crate::gun::Weapon;
+use crate::input::ActionSnapshot;
+use crate::level_pack::LevelPack;
+use crate::soko::SokoLevel;
+use crate::tilemap::TileMap;
+use crate::timer::Timer;
+use crate::{GAME_HEIGHT, GAME_WIDTH};
+use notan::draw::*;
+use notan::math::Vec2;
+use notan::prelude::*;
+
+const FADE_DURATION: f32 = 0.25;
+
+/// Everything loaded up front in `setup`, so scenes can be created mid-game without `Graphics`.
+pub struct Assets {
+    pub tilemap: TileMap,
+    pub font: Font,
+    /// Played in order in sokoban mode, the map's own puzzle first and then the starter pack
+    pub soko_levels: Vec<SokoLevel>,
+    pub weapons: Vec<Weapon>,
+    pub skeletron: SpriteSheet,
+}
+
+impl Assets {
+    pub fn load(gfx: &mut Graphics) -> Self {
+        let mut tilemap = TileMap::from_tiled(include_str!("../assets/map.json")).unwrap();
+        tilemap.setup(gfx);
+
+        let font = gfx
+            .create_font(include_bytes!("assets/alagard.ttf"))
+            .unwrap();
+
+        let mut soko_levels = vec![SokoLevel::from_tilemap(&tilemap).unwrap()];
+        let pack = LevelPack::parse(include_str!("../assets/soko/starter.xsb")).unwrap();
+        soko_levels.extend(pack.levels);
+
+        let weapons = Weapon::load_all(include_str!("../assets/weapons.json")).unwrap();
+
+        let mut skeletron = SpriteSheet::from_aseprite(
+            gfx,
+            include_str!("../assets/skeletron.json"),
+            include_bytes!("../assets/skeletron.png"),
+        )
+        .unwrap();
+        skeletron
+            .extend(include_str!("../assets/skeletron.meta.json"))
+            .unwrap();
+
+        Assets {
+            tilemap,
+            font,
+            soko_levels,
+            weapons,
+            skeletron,
+        }
+    }
+}
+
+/// What a scene gets to look at during a tick.
+pub struct Context<'a> {
+    pub input: &'a ActionSnapshot,
+    /// Mouse position in game pixels, before any camera offset
+    pub mouse: Vec2,
+    pub assets: &'a Assets,
+}
+
+/// Returned from `Scene::update` to change what's on the stack.
+pub enum Transition {
+    None,
+    /// Put a scene on top, the scenes below stop updating until it pops
+    Push(Box<dyn Scene>),
+    Pop,
+    /// Fade out, throw away the whole stack and fade in on a new scene
+    Switch(Box<dyn Scene>),
+}
+
+pub trait Scene {
+    fn enter(&mut self) {}
+
+    fn exit(&mut self) {}
+
+    fn update(&mut self, ctx: &Context, dt: f32) -> Transition;
+
+    fn draw(&self, draw: &mut Draw, alpha: f32);
+
+    /// Overlays are drawn on top of the scene below them instead of hiding it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Only the top scene updates, everything from the topmost non-overlay scene upwards is drawn.
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+    next: Option<Box<dyn Scene>>,
+    fade_out: Timer,
+    fade_in: Timer,
+}
+
+impl SceneStack {
+    pub fn new(scene: Box<dyn Scene>) -> Self {
+        let mut fade_out = Timer::new(FADE_DURATION);
+        fade_out.finish();
+        let mut fade_in = Timer::new(FADE_DURATION);
+        fade_in.finish();
+
+        let mut stack = SceneStack {
+            scenes: Vec::new(),
+            next: None,
+            fade_out,
+            fade_in,
+        };
+        stack.push(scene);
+        stack
+    }
+
+    pub fn push(&mut self, mut scene: Box<dyn Scene>) {
+        scene.enter();
+        self.scenes.push(scene);
+    }
+
+    pub fn pop(&mut self) {
+        // The bottom scene stays, there would be nothing left to draw otherwise
+        if self.scenes.len() > 1 {
+            if let Some(mut scene) = self.scenes.pop() {
+                scene.exit();
+            }
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context, dt: f32) {
+        if self.next.is_some() {
+            self.fade_out.update(dt);
+            if self.fade_out.is_finished() {
+                self.finish_switch();
+            }
+            return;
+        }
+
+        self.fade_in.update(dt);
+
+        let Some(scene) = self.scenes.last_mut() else {
+            return;
+        };
+        match scene.update(ctx, dt) {
+            Transition::None => {}
+            Transition::Push(scene) => self.push(scene),
+            Transition::Pop => self.pop(),
+            Transition::Switch(scene) => {
+                self.next = Some(scene);
+                self.fade_out.reset();
+            }
+        }
+    }
+
+    fn finish_switch(&mut self) {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.exit();
+        }
+        if let Some(scene) = self.next.take() {
+            self.push(scene);
+        }
+        self.fade_in.reset();
+    }
+
+    pub fn draw(&self, draw: &mut Draw, alpha: f32) {
+        let base = self
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &self.scenes[base..] {
+            scene.draw(draw, alpha);
+        }
+
+        let fade = if self.next.is_some() {
+            self.fade_out.progress()
+        } else {
+            1.0 - self.fade_in.progress()
+        };
+        if fade > 0.0 {
+            draw.rect((0.0, 0.0), (GAME_WIDTH as f32, GAME_HEIGHT as f32))
+                .color(Color::BLACK)
+                .alpha(fade);
+        }
+    }
+}
@@ -0,0 +1,561 @@
+use crate::camera::Camera;
+use crate::combat::{Damage, Health, Hurtbox, Team};
+use crate::enemy::Enemy;
+use crate::gun::{Gun, GunEvent, Impact};
+use crate::input::{Action, Axis};
+use crate::player::Player;
+use crate::scene::{Assets, Context, Scene, Transition};
+use crate::soko::Sokoban;
+use crate::solver::SolveError;
+use crate::textbox::{Conversation, Message};
+use crate::tilemap::{ObjectKind, TileMap, TriggerEvent};
+use crate::timer::Timer;
+use crate::top_down::TopDownPlayer;
+use crate::trigger::TriggerTracker;
+use crate::{GAME_HEIGHT, GAME_WIDTH};
+use notan::draw::*;
+use notan::math::{IVec2, Mat3, Vec2};
+use notan::prelude::*;
+
+pub struct PlatformerScene {
+    tilemap: TileMap,
+    player: Player,
+    camera: Camera,
+}
+
+impl PlatformerScene {
+    pub fn new(assets: &Assets) -> Self {
+        let tilemap = assets.tilemap.clone();
+        let spawn = tilemap.spawn_point("player").unwrap();
+
+        PlatformerScene {
+            player: Player::new(spawn.x, spawn.y, &assets.skeletron),
+            tilemap,
+            camera: Camera::new(),
+        }
+    }
+}
+
+impl Scene for PlatformerScene {
+    fn enter(&mut self) {
+        self.camera.snap(self.player.pos);
+    }
+
+    fn update(&mut self, ctx: &Context, dt: f32) -> Transition {
+        let input = ctx.input;
+        if input.pressed(Action::Pause) {
+            return Transition::Push(Box::new(PauseScene::new(ctx.assets)));
+        }
+
+        let x = input.axis(Axis::MoveX);
+        self.player
+            .move_horizontal(x < 0.0, x > 0.0, input.held(Action::Sprint), dt);
+        if input.pressed(Action::Jump) {
+            self.player.jump(input.axis(Axis::MoveY) > 0.0);
+        }
+        if input.released(Action::Jump) {
+            self.player.cancel_jump();
+        }
+        self.player
+            .update(&self.tilemap, dt, input.held(Action::Jump));
+
+        self.camera.follow(self.player.pos, dt);
+        Transition::None
+    }
+
+    fn draw(&self, draw: &mut Draw, alpha: f32) {
+        let camera = self.camera.render_pos(alpha);
+        draw.transform()
+            .push(Mat3::from_translation(camera + self.camera.shake_offset));
+
+        self.tilemap.draw_background(draw, camera);
+        self.player.draw(draw, alpha);
+        self.tilemap.draw_foreground(draw, camera);
+        self.player.render_debug(draw, &self.tilemap);
+
+        draw.transform().pop();
+    }
+}
+
+pub struct TopDownScene {
+    tilemap: TileMap,
+    player: TopDownPlayer,
+    gun: Gun,
+    triggers: TriggerTracker,
+    camera: Camera,
+    font: Font,
+    // Recent bullet impacts, drawn as sparks until their timer runs out
+    impacts: Vec<(Impact, Timer)>,
+    // Training dummies from the map's target objects, removed when their health runs out
+    targets: Vec<(Hurtbox, Health)>,
+    enemies: Vec<Enemy>,
+    explosions: Vec<(Vec2, f32, Timer)>,
+}
+
+impl TopDownScene {
+    /// Fails when the map has no `top_down` spawn point or an enemy object can't be built.
+    pub fn new(assets: &Assets) -> Result<Self, String> {
+        let tilemap = assets.tilemap.clone();
+        let spawn = tilemap
+            .spawn_point("top_down")
+            .ok_or("map has no 'top_down' spawn point")?;
+        let targets = tilemap
+            .objects
+            .iter()
+            .filter(|o| matches!(o.kind, ObjectKind::Target))
+            .map(|o| {
+                let hurtbox = Hurtbox {
+                    position: o.pos,
+                    radius: 6.0,
+                    team: Team::Enemy,
+                    owner: o.id,
+                };
+                (hurtbox, Health::new(5.0, 0.1))
+            })
+            .collect();
+        let enemies = tilemap
+            .objects
+            .iter()
+            .filter(|o| matches!(o.kind, ObjectKind::Enemy))
+            .map(|o| {
+                Enemy::from_object(o, &assets.weapons).map_err(|e| format!("enemy {}: {}", o.id, e))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(TopDownScene {
+            player: TopDownPlayer::new(spawn.x, spawn.y, &assets.skeletron),
+            tilemap,
+            gun: Gun::new(assets.weapons.clone(), Team::Player),
+            triggers: TriggerTracker::new(),
+            camera: Camera::new(),
+            font: assets.font,
+            impacts: Vec::new(),
+            targets,
+            enemies,
+            explosions: Vec::new(),
+        })
+    }
+
+    // Starting over mid-game, staying put if the scene can't be built
+    fn switch_to(assets: &Assets) -> Transition {
+        match TopDownScene::new(assets) {
+            Ok(scene) => Transition::Switch(Box::new(scene)),
+            Err(e) => {
+                eprintln!("couldn't start the top-down scene: {}", e);
+                Transition::None
+            }
+        }
+    }
+}
+
+impl Scene for TopDownScene {
+    fn enter(&mut self) {
+        self.camera.snap(self.player.pos);
+    }
+
+    fn update(&mut self, ctx: &Context, dt: f32) -> Transition {
+        let input = ctx.input;
+        if input.pressed(Action::Pause) {
+            return Transition::Push(Box::new(PauseScene::new(ctx.assets)));
+        }
+
+        self.player.move_direction(input, dt);
+        self.player.update(&self.tilemap, dt);
+
+        let mut transition = Transition::None;
+        let center = self.player.pos + self.player.size / 2.0;
+        for event in self.triggers.update(&self.tilemap, center) {
+            match event {
+                TriggerEvent::Conversation(lines) => {
+                    let messages = lines.into_iter().map(Message::Text).collect();
+                    transition =
+                        Transition::Push(Box::new(DialogueScene::new(messages, ctx.assets)));
+                }
+            }
+        }
+
+        if input.pressed(Action::SwitchWeapon) {
+            self.gun.switch_weapon();
+        }
+        if input.pressed(Action::Reload) {
+            self.gun.reload();
+        }
+        self.gun
+            .pull_trigger(input.pressed(Action::Shoot), input.held(Action::Shoot));
+
+        for (_, timer) in &mut self.impacts {
+            timer.update(dt);
+        }
+        self.impacts.retain(|(_, timer)| !timer.is_finished());
+        for (_, _, timer) in &mut self.explosions {
+            timer.update(dt);
+        }
+        self.explosions.retain(|(_, _, timer)| !timer.is_finished());
+        for (_, health) in &mut self.targets {
+            health.update(dt);
+        }
+
+        // Targets first, then enemies, then the player, hits are matched back up by index
+        let mut hurtboxes: Vec<Hurtbox> =
+            self.targets.iter().map(|(hurtbox, _)| *hurtbox).collect();
+        hurtboxes.extend(self.enemies.iter().map(|enemy| enemy.hurtbox()));
+        let player_hurtbox = self.player.hurtbox();
+        hurtboxes.push(player_hurtbox);
+
+        let mouse = ctx.mouse - self.camera.pos;
+        let mut events = self.gun.update(
+            &self.tilemap,
+            &hurtboxes,
+            self.player.muzzle(1.0).into(),
+            mouse.into(),
+            dt,
+        );
+        for enemy in &mut self.enemies {
+            let enemy_events = enemy.update(&self.tilemap, center, &hurtboxes, dt);
+            // Recoil and shake are only for the player's own shots
+            events.extend(
+                enemy_events
+                    .into_iter()
+                    .filter(|event| !matches!(event, GunEvent::Fired { .. })),
+            );
+
+            // Running into an enemy hurts too
+            let hurtbox = enemy.hurtbox();
+            let reach = hurtbox.radius + player_hurtbox.radius;
+            if hurtbox.position.distance(player_hurtbox.position) <= reach {
+                let away = (player_hurtbox.position - hurtbox.position).normalize_or_zero();
+                let damage = Damage {
+                    amount: 1.0,
+                    knockback: away * 3.0,
+                };
+                self.player.hurt(&damage);
+            }
+        }
+
+        for event in events {
+            match event {
+                GunEvent::Fired {
+                    direction,
+                    recoil,
+                    shake,
+                } => {
+                    self.player.velocity -= direction * recoil;
+                    self.camera.shake(shake);
+                }
+                GunEvent::Impact(impact) => self.impacts.push((impact, Timer::new(0.15))),
+                GunEvent::Hit { hit, velocity } => {
+                    let enemy = hit.target.checked_sub(self.targets.len());
+                    let landed = if let Some((_, health)) = self.targets.get_mut(hit.target) {
+                        health.take(&hit.damage)
+                    } else if let Some(enemy) = enemy.and_then(|i| self.enemies.get_mut(i)) {
+                        enemy.velocity += hit.damage.knockback;
+                        enemy.health.take(&hit.damage)
+                    } else {
+                        self.player.hurt(&hit.damage)
+                    };
+                    if landed && velocity != Vec2::ZERO {
+                        let impact = Impact {
+                            position: hit.position,
+                            normal: -velocity.normalize(),
+                            velocity,
+                        };
+                        self.impacts.push((impact, Timer::new(0.15)));
+                    }
+                }
+                GunEvent::Explosion { position, radius } => {
+                    self.explosions.push((position, radius, Timer::new(0.25)));
+                    self.camera.shake(radius / 4.0);
+                }
+            }
+        }
+
+        self.targets.retain(|(_, health)| !health.is_dead());
+        self.enemies.retain(|enemy| !enemy.health.is_dead());
+
+        if self.player.health.is_dead() {
+            return TopDownScene::switch_to(ctx.assets);
+        }
+
+        self.camera.follow(self.player.pos, dt);
+        transition
+    }
+
+    fn draw(&self, draw: &mut Draw, alpha: f32) {
+        let camera = self.camera.render_pos(alpha);
+        draw.transform()
+            .push(Mat3::from_translation(camera + self.camera.shake_offset));
+
+        self.tilemap.draw_background(draw, camera);
+
+        for (target, health) in &self.targets {
+            // Flashes white while invulnerable from the last hit
+            let color = if health.is_invulnerable() {
+                Color::WHITE
+            } else {
+                Color::RED
+            };
+            draw.circle(target.radius)
+                .position(target.position.x, target.position.y)
+                .color(color);
+        }
+
+        for enemy in &self.enemies {
+            enemy.draw(draw, alpha);
+        }
+
+        self.player.draw(draw, alpha);
+        self.gun.draw(draw, self.player.muzzle(alpha).into(), alpha);
+        for (impact, timer) in &self.impacts {
+            // Faster bullets throw longer sparks
+            let length = impact.velocity.length() * 0.012 * (1.0 - timer.progress());
+            let end = impact.position + impact.normal * length;
+            draw.line(impact.position.into(), end.into())
+                .width(2.0)
+                .color(Color::YELLOW);
+        }
+        for (position, radius, timer) in &self.explosions {
+            // Grows out to the full radius while fading
+            let progress = timer.progress();
+            draw.circle(radius * progress.sqrt())
+                .position(position.x, position.y)
+                .stroke(2.0)
+                .color(Color::ORANGE)
+                .alpha(1.0 - progress);
+        }
+
+        self.tilemap.draw_foreground(draw, camera);
+
+        self.player.render_debug(draw, &self.tilemap);
+        if let Some(hit) = self.gun.aim_hit {
+            let tile_size = self.tilemap.tile_size;
+            draw.rect(
+                (hit.tile.as_vec2() * tile_size).into(),
+                (tile_size, tile_size),
+            )
+            .stroke_color(Color::YELLOW);
+        }
+
+        draw.transform().pop();
+
+        let weapon = self.gun.weapon();
+        let ammo = if self.gun.reloading {
+            "reloading".to_string()
+        } else {
+            format!(
+                "{}/{}",
+                self.gun.ammo[self.gun.current], weapon.magazine_size
+            )
+        };
+        let health = &self.player.health;
+        draw.text(
+            &self.font,
+            &format!(
+                "{} {}  hp {}/{}",
+                weapon.name, ammo, health.current, health.max
+            ),
+        )
+        .position(4.0, GAME_HEIGHT as f32 - 4.0)
+        .size(16.0)
+        .h_align_left()
+        .v_align_bottom();
+    }
+}
+
+pub struct SokobanScene {
+    sokoban: Sokoban,
+    /// Position in `Assets::soko_levels`
+    level: usize,
+    camera: Camera,
+    font: Font,
+    // Gives the last push a moment to land before moving on
+    win_timer: Timer,
+    complete: bool,
+}
+
+impl SokobanScene {
+    pub fn new(assets: &Assets, level: usize) -> Self {
+        SokobanScene {
+            sokoban: Sokoban::new(assets.soko_levels[level].clone()),
+            level,
+            camera: Camera::new(),
+            font: assets.font,
+            win_timer: Timer::new(1.0),
+            complete: false,
+        }
+    }
+}
+
+impl Scene for SokobanScene {
+    fn enter(&mut self) {
+        // Levels fit on screen, so the camera stays on the center of the board
+        let level = &self.sokoban.level;
+        let size = IVec2::new(level.width, level.height).as_vec2() * level.cell_size;
+        self.camera.snap(size / 2.0);
+    }
+
+    fn update(&mut self, ctx: &Context, dt: f32) -> Transition {
+        if ctx.input.pressed(Action::Pause) {
+            return Transition::Push(Box::new(PauseScene::new(ctx.assets)));
+        }
+
+        if ctx.input.pressed(Action::Hint) {
+            if let Err(e) = self.sokoban.hint() {
+                let text = match e {
+                    SolveError::GaveUp(_) => "This one's too tricky for a hint.",
+                    SolveError::Stuck | SolveError::NoSolution => {
+                        "There's no way out from here, try undoing."
+                    }
+                };
+                let messages = vec![Message::Text(text.to_string())];
+                return Transition::Push(Box::new(DialogueScene::new(messages, ctx.assets)));
+            }
+        }
+
+        self.sokoban.update(dt, ctx.input);
+
+        if !self.sokoban.is_solved() {
+            self.win_timer.reset();
+            return Transition::None;
+        }
+
+        self.win_timer.update(dt);
+        if !self.win_timer.is_finished() || self.complete {
+            return Transition::None;
+        }
+
+        let next = self.level + 1;
+        if next < ctx.assets.soko_levels.len() {
+            Transition::Switch(Box::new(SokobanScene::new(ctx.assets, next)))
+        } else {
+            self.complete = true;
+            let messages = vec![Message::Text("You solved every level!".to_string())];
+            Transition::Push(Box::new(DialogueScene::new(messages, ctx.assets)))
+        }
+    }
+
+    fn draw(&self, draw: &mut Draw, alpha: f32) {
+        let camera = self.camera.render_pos(alpha);
+        draw.transform().push(Mat3::from_translation(camera));
+
+        self.sokoban.draw(draw);
+
+        draw.transform().pop();
+
+        let title = if self.sokoban.is_solved() {
+            format!("{} - solved!", self.sokoban.level.title)
+        } else {
+            self.sokoban.level.title.clone()
+        };
+        draw.text(&self.font, &title)
+            .position(4.0, GAME_HEIGHT as f32 - 4.0)
+            .size(16.0)
+            .h_align_left()
+            .v_align_bottom();
+    }
+}
+
+/// Shows a conversation over the scene below and pops itself after the last message.
+pub struct DialogueScene {
+    conversation: Conversation,
+}
+
+impl DialogueScene {
+    pub fn new(messages: Vec<Message>, assets: &Assets) -> Self {
+        DialogueScene {
+            conversation: Conversation::new(messages, assets.font),
+        }
+    }
+}
+
+impl Scene for DialogueScene {
+    fn update(&mut self, ctx: &Context, dt: f32) -> Transition {
+        if ctx.input.pressed(Action::AdvanceDialogue)
+            && self.conversation.textbox.finished_printing()
+        {
+            if self.conversation.on_last_message() {
+                return Transition::Pop;
+            }
+            self.conversation.advance();
+        }
+
+        self.conversation.update(dt);
+        Transition::None
+    }
+
+    fn draw(&self, draw: &mut Draw, _alpha: f32) {
+        self.conversation.draw(draw);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+const PAUSE_OPTIONS: [&str; 4] = ["Resume", "Platformer", "Top-down", "Sokoban"];
+
+/// Pause menu, also the way to switch between game modes.
+pub struct PauseScene {
+    selected: usize,
+    font: Font,
+}
+
+impl PauseScene {
+    pub fn new(assets: &Assets) -> Self {
+        PauseScene {
+            selected: 0,
+            font: assets.font,
+        }
+    }
+}
+
+impl Scene for PauseScene {
+    fn update(&mut self, ctx: &Context, _dt: f32) -> Transition {
+        let input = ctx.input;
+        if input.pressed(Action::Pause) {
+            return Transition::Pop;
+        }
+
+        let dy = input.axis_pressed(Axis::MoveY);
+        if dy < 0.0 {
+            self.selected = (self.selected + PAUSE_OPTIONS.len() - 1) % PAUSE_OPTIONS.len();
+        } else if dy > 0.0 {
+            self.selected = (self.selected + 1) % PAUSE_OPTIONS.len();
+        }
+
+        if !input.pressed(Action::Confirm) {
+            return Transition::None;
+        }
+        match self.selected {
+            1 => Transition::Switch(Box::new(PlatformerScene::new(ctx.assets))),
+            2 => TopDownScene::switch_to(ctx.assets),
+            3 => Transition::Switch(Box::new(SokobanScene::new(ctx.assets, 0))),
+            _ => Transition::Pop,
+        }
+    }
+
+    fn draw(&self, draw: &mut Draw, _alpha: f32) {
+        draw.rect((0.0, 0.0), (GAME_WIDTH as f32, GAME_HEIGHT as f32))
+            .color(Color::BLACK)
+            .alpha(0.6);
+
+        let font_size = 16.0;
+        let top = GAME_HEIGHT as f32 / 2.0 - PAUSE_OPTIONS.len() as f32 * font_size / 2.0;
+        for (i, option) in PAUSE_OPTIONS.iter().enumerate() {
+            let color = if i == self.selected {
+                Color::YELLOW
+            } else {
+                Color::WHITE
+            };
+            draw.text(&self.font, option)
+                .position(GAME_WIDTH as f32 / 2.0, top + i as f32 * font_size)
+                .size(font_size)
+                .color(color)
+                .h_align_center()
+                .v_align_top();
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
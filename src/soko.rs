@@ -1,18 +1,409 @@
-use crate::tilemap::{TileMap, TileType, TILE_SIZE};
+use crate::input::{Action, ActionSnapshot, Axis};
+use crate::solver::{self, Solution, SolveError};
+use crate::tilemap::{ObjectKind, TileMap, TileType, TILE_SIZE};
 use crate::timer::Timer;
 use notan::draw::*;
-use notan::math::Vec2;
+use notan::math::{IVec2, Vec2};
 use notan::prelude::*;
 use simple_easing::*;
 use spring_motion::*;
 
-enum Direction {
+/// States a hint explores before giving up. It searches on the game thread when the hint key
+/// is pressed, so this keeps a hard position from freezing the game for seconds.
+pub const HINT_SEARCH_LIMIT: usize = 20_000;
+
+pub enum Direction {
     Up,
     Down,
     Left,
     Right,
 }
 
+/// The parts of a sokoban level that never change: walls, goals and where everything starts.
+/// Cells are in grid units, anything outside the grid counts as a wall.
+#[derive(Clone)]
+pub struct SokoLevel {
+    pub title: String,
+    pub width: i32,
+    pub height: i32,
+    walls: Vec<Vec<bool>>,
+    pub goals: Vec<IVec2>,
+    pub player_start: IVec2,
+    pub crate_starts: Vec<IVec2>,
+    /// Size of a grid cell when drawn
+    pub cell_size: f32,
+}
+
+impl SokoLevel {
+    /// Solid tiles become walls, crates and goals come from `crate`/`goal` objects and the
+    /// player starts at the `soko` spawn point.
+    pub fn from_tilemap(tilemap: &TileMap) -> Result<Self, String> {
+        let cell_of = |pos: Vec2| (pos / tilemap.tile_size).floor().as_ivec2();
+
+        let walls = tilemap
+            .tiles
+            .iter()
+            .map(|row| row.iter().map(|tile| *tile == TileType::Solid).collect())
+            .collect();
+        let player_start = tilemap
+            .spawn_point("soko")
+            .map(cell_of)
+            .ok_or("map has no 'soko' spawn point")?;
+
+        let mut goals = Vec::new();
+        let mut crate_starts = Vec::new();
+        for object in &tilemap.objects {
+            match object.kind {
+                ObjectKind::Crate => crate_starts.push(cell_of(object.pos)),
+                ObjectKind::Goal => goals.push(cell_of(object.pos)),
+                _ => {}
+            }
+        }
+
+        Ok(SokoLevel {
+            title: "Map".to_string(),
+            width: tilemap.width as i32,
+            height: tilemap.height as i32,
+            walls,
+            goals,
+            player_start,
+            crate_starts,
+            cell_size: tilemap.tile_size,
+        })
+    }
+
+    /// Parses one level in the XSB text format: `#` wall, `$` crate, `.` goal, `@` player,
+    /// `*` crate on a goal and `+` player on a goal. Spaces, `-` and `_` are floor.
+    pub fn from_xsb(title: &str, text: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = text.lines().filter(|row| !row.trim().is_empty()).collect();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+
+        let mut walls = vec![vec![false; width]; rows.len()];
+        let mut goals = Vec::new();
+        let mut crate_starts = Vec::new();
+        let mut player_start = None;
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let cell = IVec2::new(x as i32, y as i32);
+                match c {
+                    '#' => walls[y][x] = true,
+                    ' ' | '-' | '_' => {}
+                    '.' => goals.push(cell),
+                    '$' => crate_starts.push(cell),
+                    '*' => {
+                        goals.push(cell);
+                        crate_starts.push(cell);
+                    }
+                    '@' | '+' => {
+                        if player_start.replace(cell).is_some() {
+                            return Err(format!("level '{}' has more than one player", title));
+                        }
+                        if c == '+' {
+                            goals.push(cell);
+                        }
+                    }
+                    _ => return Err(format!("level '{}' has an unknown tile '{}'", title, c)),
+                }
+            }
+        }
+
+        let Some(player_start) = player_start else {
+            return Err(format!("level '{}' has no player", title));
+        };
+        if crate_starts.is_empty() || crate_starts.len() != goals.len() {
+            return Err(format!(
+                "level '{}' has {} crates for {} goals",
+                title,
+                crate_starts.len(),
+                goals.len()
+            ));
+        }
+
+        Ok(SokoLevel {
+            title: title.to_string(),
+            width: width as i32,
+            height: rows.len() as i32,
+            walls,
+            goals,
+            player_start,
+            crate_starts,
+            cell_size: TILE_SIZE,
+        })
+    }
+
+    pub fn is_wall(&self, cell: IVec2) -> bool {
+        if cell.x < 0 || cell.y < 0 || cell.x >= self.width || cell.y >= self.height {
+            return true;
+        }
+        self.walls[cell.y as usize][cell.x as usize]
+    }
+
+    pub fn is_goal(&self, cell: IVec2) -> bool {
+        self.goals.contains(&cell)
+    }
+
+    pub fn draw(&self, draw: &mut Draw) {
+        let size = self.cell_size;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = IVec2::new(x, y);
+                let pos = (x as f32 * size, y as f32 * size);
+                if self.is_wall(cell) {
+                    draw.rect(pos, (size, size)).color(Color::GRAY);
+                } else if self.is_goal(cell) {
+                    draw.rect(pos, (size, size)).stroke_color(Color::YELLOW);
+                }
+            }
+        }
+    }
+}
+
+/// One step of the player, with enough information to play it back in either direction.
+#[derive(Clone, Copy, Debug)]
+struct SokoMove {
+    step: IVec2,
+    /// Index of the crate pushed by this step, if any
+    pushed: Option<usize>,
+}
+
+/// A sokoban level in play: the player, the crates and the rules for moving them.
+pub struct Sokoban {
+    pub level: SokoLevel,
+    pub player: SokoPlayer,
+    pub crates: Vec<Crate>,
+    history: Vec<SokoMove>,
+    undone: Vec<SokoMove>,
+    // What's left of the last hint's solution, last step first. Cleared by anything that moves
+    // the player other than following it, along with a failed search
+    hint: Option<Result<Vec<IVec2>, SolveError>>,
+}
+
+impl Sokoban {
+    pub fn new(level: SokoLevel) -> Self {
+        let player = SokoPlayer::new(level.player_start.x, level.player_start.y);
+        let crates = level
+            .crate_starts
+            .iter()
+            .map(|&cell| Crate::new(cell))
+            .collect();
+
+        Sokoban {
+            level,
+            player,
+            crates,
+            history: Vec::new(),
+            undone: Vec::new(),
+            hint: None,
+        }
+    }
+
+    pub fn crate_at(&self, cell: IVec2) -> Option<usize> {
+        self.crates.iter().position(|c| c.cell == cell)
+    }
+
+    /// Steps the player one cell, pushing a crate if there is one in the way.
+    /// Returns false when a wall, or a crate that can't move, blocks the step.
+    pub fn try_move(&mut self, step: IVec2) -> bool {
+        self.player.face(step);
+
+        let target = IVec2::new(self.player.x, self.player.y) + step;
+        if self.level.is_wall(target) {
+            return false;
+        }
+
+        let pushed = self.crate_at(target);
+        if pushed.is_some() {
+            let behind = target + step;
+            if self.level.is_wall(behind) || self.crate_at(behind).is_some() {
+                return false;
+            }
+        }
+
+        let mv = SokoMove { step, pushed };
+        self.apply(mv, 1);
+        self.history.push(mv);
+        self.undone.clear();
+        true
+    }
+
+    /// Takes back the last move. Everything springs back to where it was rather than snapping.
+    pub fn undo(&mut self) {
+        if let Some(mv) = self.history.pop() {
+            self.apply(mv, -1);
+            self.undone.push(mv);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(mv) = self.undone.pop() {
+            self.apply(mv, 1);
+            self.history.push(mv);
+        }
+    }
+
+    /// Puts the level back to its starting layout and forgets the move history.
+    pub fn restart(&mut self) {
+        let start = self.level.player_start;
+        self.player
+            .move_player(start.x - self.player.x, start.y - self.player.y);
+        for (c, &start) in self.crates.iter_mut().zip(&self.level.crate_starts) {
+            c.move_to(start);
+        }
+
+        self.history.clear();
+        self.undone.clear();
+        self.hint = None;
+    }
+
+    /// Solves the level from the current position rather than from the start.
+    pub fn solve(&self, limit: usize) -> Result<Solution, SolveError> {
+        let crates: Vec<IVec2> = self.crates.iter().map(|c| c.cell).collect();
+        solver::solve(
+            &self.level,
+            IVec2::new(self.player.x, self.player.y),
+            &crates,
+            limit,
+        )
+    }
+
+    /// Takes the next step towards a solution from the current position. The solver only runs
+    /// for the first hint after some other move, following hints reuses its solution.
+    pub fn hint(&mut self) -> Result<(), SolveError> {
+        let mut hint = match self.hint.take() {
+            Some(hint) => hint,
+            None => self.solve(HINT_SEARCH_LIMIT).map(|solution| {
+                let mut moves = solution.moves;
+                moves.reverse();
+                moves
+            }),
+        };
+        let step = match &mut hint {
+            Ok(moves) => moves.pop(),
+            Err(e) => {
+                let e = e.clone();
+                self.hint = Some(hint);
+                return Err(e);
+            }
+        };
+
+        // Moving clears the hint, so it's put back after
+        if let Some(step) = step {
+            self.try_move(step);
+        }
+        self.hint = Some(hint);
+        Ok(())
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.crates.iter().all(|c| self.level.is_goal(c.cell))
+    }
+
+    // `direction` is 1 to play the move forwards and -1 to play it backwards
+    fn apply(&mut self, mv: SokoMove, direction: i32) {
+        self.hint = None;
+        let delta = mv.step * direction;
+        if let Some(index) = mv.pushed {
+            let c = &mut self.crates[index];
+            c.move_to(c.cell + delta);
+        }
+        self.player.move_player(delta.x, delta.y);
+    }
+
+    pub fn update(&mut self, dt: f32, input: &ActionSnapshot) {
+        if input.pressed(Action::Undo) {
+            self.undo();
+        } else if input.pressed(Action::Redo) {
+            self.redo();
+        } else if input.pressed(Action::Restart) {
+            self.restart();
+        }
+
+        let dx = input.axis_pressed(Axis::MoveX);
+        let dy = input.axis_pressed(Axis::MoveY);
+
+        if dx < 0.0 {
+            self.try_move(IVec2::new(-1, 0));
+        } else if dx > 0.0 {
+            self.try_move(IVec2::new(1, 0));
+        } else if dy < 0.0 {
+            self.try_move(IVec2::new(0, -1));
+        } else if dy > 0.0 {
+            self.try_move(IVec2::new(0, 1));
+        }
+
+        self.player.update(dt);
+        for c in &mut self.crates {
+            c.update(dt);
+        }
+    }
+
+    pub fn draw(&self, draw: &mut Draw) {
+        let cell_size = self.level.cell_size;
+
+        self.level.draw(draw);
+        for c in &self.crates {
+            c.draw(draw, cell_size, self.level.is_goal(c.cell));
+        }
+        self.player.draw(draw, cell_size);
+    }
+}
+
+pub struct Crate {
+    pub cell: IVec2,
+    pub render_position: Vec2,
+    render_spring: SpringSystem<Vec2>,
+    movement_animation: Timer,
+}
+
+impl Crate {
+    pub fn new(cell: IVec2) -> Self {
+        let mut movement_animation = Timer::new(0.2);
+        movement_animation.finish();
+
+        Crate {
+            cell,
+            render_position: cell.as_vec2(),
+            render_spring: SpringSystem::new(soko_spring(), cell.as_vec2(), Vec2::splat(0.)),
+            movement_animation,
+        }
+    }
+
+    pub fn move_to(&mut self, cell: IVec2) {
+        self.cell = cell;
+        self.movement_animation.reset();
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.render_position = self.render_spring.step_clamped(dt, self.cell.as_vec2());
+        self.movement_animation.update(dt);
+    }
+
+    pub fn draw(&self, draw: &mut Draw, cell_size: f32, on_goal: bool) {
+        let pos = self.render_position;
+        let t = self.movement_animation.progress();
+
+        let rotation = 0.1 * (1.0 - cubic_out(t));
+        let scale = if self.movement_animation.is_finished() {
+            1.0
+        } else {
+            push_scale(t)
+        };
+
+        let width = cell_size * scale;
+        let height = cell_size * scale;
+        let color = if on_goal { Color::GREEN } else { Color::ORANGE };
+
+        draw.rect((0., 0.), (width, height))
+            .color(color)
+            .rotate(rotation)
+            .translate(
+                pos.x * cell_size + (cell_size - width) / 2.0,
+                pos.y * cell_size + (cell_size - height) / 2.0,
+            );
+    }
+}
+
 pub struct SokoPlayer {
     pub x: i32,
     pub y: i32,
@@ -30,12 +421,8 @@ impl SokoPlayer {
             y,
             render_position: Vec2::new(x as f32, y as f32),
             render_spring: SpringSystem::new(
-                SpringDescription {
-                    frequency: 3.,
-                    damping: 0.4,
-                    initial_response: -0.1,
-                },
-                Vec2::splat(0.),
+                soko_spring(),
+                Vec2::new(x as f32, y as f32),
                 Vec2::splat(0.),
             ),
             direction: Direction::Right,
@@ -51,21 +438,19 @@ impl SokoPlayer {
         self.movement_animation.reset();
     }
 
-    pub fn update(&mut self, dt: f32, left: bool, right: bool, up: bool, down: bool) {
-        if left {
-            self.move_player(-1, 0);
-            self.direction = Direction::Left;
-        } else if right {
-            self.move_player(1, 0);
-            self.direction = Direction::Right;
-        } else if up {
-            self.move_player(0, -1);
-            self.direction = Direction::Up;
-        } else if down {
-            self.move_player(0, 1);
-            self.direction = Direction::Down;
-        }
+    pub fn face(&mut self, step: IVec2) {
+        self.direction = if step.x < 0 {
+            Direction::Left
+        } else if step.x > 0 {
+            Direction::Right
+        } else if step.y < 0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+    }
 
+    pub fn update(&mut self, dt: f32) {
         self.render_position = self
             .render_spring
             .step_clamped(dt, Vec2::new(self.x as f32, self.y as f32));
@@ -74,7 +459,7 @@ impl SokoPlayer {
         self.landing_animation.update(dt);
     }
 
-    pub fn draw(&self, draw: &mut Draw) {
+    pub fn draw(&self, draw: &mut Draw, cell_size: f32) {
         let pos = self.render_position;
 
         // let scale = 0.5 + 0.5 * cubic_out(self.movement_animation.progress());
@@ -92,28 +477,149 @@ impl SokoPlayer {
                 let t = t * t;
                 0.8 + 0.2 * t * (2.0 - t) // Starts at 0.8 and animates back to 1.0
             } else if !self.movement_animation.is_finished() {
-                let t = self.movement_animation.progress();
-                let t = t * t;
-                -0.5 * t * (t - 1.0) + 1.0
+                push_scale(self.movement_animation.progress())
             } else {
                 1.0
             };
 
-        let width = TILE_SIZE * scale;
-        let height = TILE_SIZE * scale;
+        let width = cell_size * scale;
+        let height = cell_size * scale;
 
+        // Hops half a cell high at the peak of the move
         let offset = {
             let t = self.movement_animation.progress();
             let t = t * t;
-            -32.0 * t * (t - 1.0)
+            -2.0 * cell_size * t * (t - 1.0)
         };
 
         draw.rect((0., 0.), (width, height))
             .color(Color::WHITE)
             .rotate(rotation)
             .translate(
-                pos.x * TILE_SIZE - width / 2.0,
-                pos.y * TILE_SIZE - height / 2.0 - offset,
+                pos.x * cell_size + (cell_size - width) / 2.0,
+                pos.y * cell_size + (cell_size - height) / 2.0 - offset,
             );
     }
 }
+
+fn soko_spring() -> SpringDescription<f32> {
+    SpringDescription {
+        frequency: 3.,
+        damping: 0.4,
+        initial_response: -0.1,
+    }
+}
+
+// Bulges out and back in over the course of a move
+fn push_scale(progress: f32) -> f32 {
+    let t = progress * progress;
+    -0.5 * t * (t - 1.0) + 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sokoban() -> Sokoban {
+        let level = SokoLevel::from_xsb("test", "######\n#@$ .#\n######").unwrap();
+        Sokoban::new(level)
+    }
+
+    // Where the player and every crate are
+    fn layout(sokoban: &Sokoban) -> (IVec2, Vec<IVec2>) {
+        let player = IVec2::new(sokoban.player.x, sokoban.player.y);
+        (player, sokoban.crates.iter().map(|c| c.cell).collect())
+    }
+
+    fn corridor() -> Sokoban {
+        let level = SokoLevel::from_xsb("test", "#######\n#@$  .#\n#######").unwrap();
+        Sokoban::new(level)
+    }
+
+    #[test]
+    fn undo_and_redo_replay_pushes() {
+        let mut sokoban = corridor();
+        let start = layout(&sokoban);
+        assert!(sokoban.try_move(IVec2::new(1, 0)));
+        let pushed = layout(&sokoban);
+        assert_eq!(pushed, (IVec2::new(2, 1), vec![IVec2::new(3, 1)]));
+
+        sokoban.undo();
+        assert_eq!(layout(&sokoban), start);
+        sokoban.redo();
+        assert_eq!(layout(&sokoban), pushed);
+
+        // Nothing left to redo
+        sokoban.redo();
+        assert_eq!(layout(&sokoban), pushed);
+    }
+
+    #[test]
+    fn moving_after_undo_forgets_the_redo() {
+        let mut sokoban = corridor();
+        sokoban.try_move(IVec2::new(1, 0));
+        sokoban.try_move(IVec2::new(1, 0));
+        sokoban.undo();
+        sokoban.undo();
+
+        assert!(sokoban.try_move(IVec2::new(1, 0)));
+        assert!(sokoban.undone.is_empty());
+        sokoban.redo();
+        assert_eq!(layout(&sokoban).0, IVec2::new(2, 1));
+    }
+
+    #[test]
+    fn restart_resets_the_layout_and_history() {
+        let mut sokoban = corridor();
+        let start = layout(&sokoban);
+        sokoban.try_move(IVec2::new(1, 0));
+        sokoban.try_move(IVec2::new(1, 0));
+        sokoban.undo();
+
+        sokoban.restart();
+        assert_eq!(layout(&sokoban), start);
+        assert!(sokoban.history.is_empty());
+        assert!(sokoban.undone.is_empty());
+        sokoban.undo();
+        assert_eq!(layout(&sokoban), start);
+    }
+
+    #[test]
+    fn following_hints_reuses_the_solution() {
+        let mut sokoban = sokoban();
+        sokoban.hint().unwrap();
+        assert_eq!(sokoban.hint, Some(Ok(vec![IVec2::new(1, 0)])));
+        sokoban.hint().unwrap();
+        assert!(sokoban.is_solved());
+    }
+
+    #[test]
+    fn moving_forgets_the_hint() {
+        let mut sokoban = sokoban();
+        sokoban.hint().unwrap();
+        sokoban.undo();
+        assert_eq!(sokoban.hint, None);
+
+        sokoban.hint().unwrap();
+        sokoban.restart();
+        assert_eq!(sokoban.hint, None);
+    }
+
+    #[test]
+    fn failed_searches_are_kept_until_a_move() {
+        let level = SokoLevel::from_xsb("test", "#####\n#@ $#\n#.  #\n#####").unwrap();
+        let mut sokoban = Sokoban::new(level);
+        assert_eq!(sokoban.hint(), Err(SolveError::Stuck));
+        assert_eq!(sokoban.hint, Some(Err(SolveError::Stuck)));
+
+        sokoban.try_move(IVec2::new(0, 1));
+        assert_eq!(sokoban.hint, None);
+    }
+
+    #[test]
+    fn searches_stop_at_the_limit_they_are_given() {
+        let sokoban = corridor();
+        assert_eq!(sokoban.solve(1).err(), Some(SolveError::GaveUp(1)));
+        assert_eq!(sokoban.solve(HINT_SEARCH_LIMIT).unwrap().pushes, 3);
+    }
+}
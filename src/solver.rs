@@ -0,0 +1,397 @@
+use crate::soko::SokoLevel;
+use notan::math::IVec2;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+
+/// States `faba validate` explores before giving up on a level. Far too many to search while
+/// the game is running, see `soko::HINT_SEARCH_LIMIT`.
+pub const VALIDATE_SEARCH_LIMIT: usize = 500_000;
+
+const DIRECTIONS: [IVec2; 4] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolveError {
+    /// A crate is already somewhere it can never be pushed to a goal from
+    Stuck,
+    /// Searched through this many states without finding a solution or running out
+    GaveUp(usize),
+    NoSolution,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::Stuck => write!(f, "a crate is stuck where it can never reach a goal"),
+            SolveError::GaveUp(states) => write!(f, "gave up after {} states", states),
+            SolveError::NoSolution => write!(f, "no solution"),
+        }
+    }
+}
+
+pub struct Solution {
+    /// Every step the player takes, in order
+    pub moves: Vec<IVec2>,
+    pub pushes: usize,
+}
+
+/// Finds a solution with the fewest pushes, using A* over crate positions. Walking between
+/// pushes takes the shortest path, but the total move count isn't guaranteed to be the minimum.
+/// States with a crate on a dead square or frozen off a goal are pruned. Gives up after
+/// exploring `limit` states.
+pub fn solve(
+    level: &SokoLevel,
+    player: IVec2,
+    crates: &[IVec2],
+    limit: usize,
+) -> Result<Solution, SolveError> {
+    let board = Board::new(level);
+    let mut start_crates: Vec<usize> = crates.iter().map(|&c| board.index(c)).collect();
+    start_crates.sort_unstable();
+
+    if start_crates.iter().any(|&c| board.dead[c]) {
+        return Err(SolveError::Stuck);
+    }
+
+    let player = board.index(player);
+    let start = Node {
+        crates: start_crates.clone(),
+        player: board.normalized_player(player, &start_crates),
+        parent: None,
+        push: None,
+        pushes: 0,
+    };
+
+    let mut nodes = vec![start];
+    let mut best: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+    best.insert((nodes[0].player, nodes[0].crates.clone()), 0);
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((board.heuristic(&nodes[0].crates), 0)));
+
+    while let Some(Reverse((_, id))) = open.pop() {
+        let node = &nodes[id];
+        if best.get(&(node.player, node.crates.clone())) != Some(&node.pushes) {
+            continue;
+        }
+        if node.crates.iter().all(|&c| board.goal[c]) {
+            return Ok(board.replay(&nodes, id, player));
+        }
+        if nodes.len() > limit {
+            return Err(SolveError::GaveUp(limit));
+        }
+
+        let reachable = board.reachable(node.player, &node.crates);
+        let mut children = Vec::new();
+        for (i, &c) in node.crates.iter().enumerate() {
+            for dir in DIRECTIONS {
+                let (Some(from), Some(to)) = (board.step(c, -dir), board.step(c, dir)) else {
+                    continue;
+                };
+                if !reachable[from] || board.wall[to] || board.dead[to] || node.crates.contains(&to)
+                {
+                    continue;
+                }
+
+                let mut crates = node.crates.clone();
+                crates[i] = to;
+                if board.is_freeze_deadlock(to, &crates) {
+                    continue;
+                }
+                crates.sort_unstable();
+
+                children.push(Node {
+                    player: board.normalized_player(c, &crates),
+                    crates,
+                    parent: Some(id),
+                    push: Some((c, dir)),
+                    pushes: node.pushes + 1,
+                });
+            }
+        }
+
+        for child in children {
+            let key = (child.player, child.crates.clone());
+            if best.get(&key).is_some_and(|&pushes| pushes <= child.pushes) {
+                continue;
+            }
+            best.insert(key, child.pushes);
+            open.push(Reverse((
+                child.pushes + board.heuristic(&child.crates),
+                nodes.len(),
+            )));
+            nodes.push(child);
+        }
+    }
+
+    Err(SolveError::NoSolution)
+}
+
+struct Node {
+    crates: Vec<usize>,
+    /// Top-left-most cell the player can reach, so states that only differ by where the
+    /// player is standing in the same area count as one
+    player: usize,
+    parent: Option<usize>,
+    /// Cell of the pushed crate before the push, and the push direction
+    push: Option<(usize, IVec2)>,
+    pushes: usize,
+}
+
+/// The level flattened into cell indices, plus the squares a crate can never leave.
+struct Board {
+    width: i32,
+    height: i32,
+    wall: Vec<bool>,
+    goal: Vec<bool>,
+    goals: Vec<IVec2>,
+    /// Squares a crate can't be pushed to a goal from, corners that aren't goals among them
+    dead: Vec<bool>,
+}
+
+impl Board {
+    fn new(level: &SokoLevel) -> Self {
+        let mut board = Board {
+            width: level.width,
+            height: level.height,
+            wall: Vec::new(),
+            goal: Vec::new(),
+            goals: level.goals.clone(),
+            dead: Vec::new(),
+        };
+        for y in 0..level.height {
+            for x in 0..level.width {
+                let cell = IVec2::new(x, y);
+                board.wall.push(level.is_wall(cell));
+                board.goal.push(level.is_goal(cell));
+            }
+        }
+
+        // Pull a crate backwards from every goal, any square it never reaches is dead
+        let mut alive = vec![false; board.wall.len()];
+        let mut queue: VecDeque<usize> = (0..board.goal.len()).filter(|&c| board.goal[c]).collect();
+        for &goal in &queue {
+            alive[goal] = true;
+        }
+        while let Some(c) = queue.pop_front() {
+            for dir in DIRECTIONS {
+                let (Some(from), Some(player)) = (board.step(c, dir), board.step(c, dir * 2))
+                else {
+                    continue;
+                };
+                if !alive[from] && !board.wall[from] && !board.wall[player] {
+                    alive[from] = true;
+                    queue.push_back(from);
+                }
+            }
+        }
+        board.dead = alive.iter().map(|alive| !alive).collect();
+        board
+    }
+
+    fn index(&self, cell: IVec2) -> usize {
+        (cell.y * self.width + cell.x) as usize
+    }
+
+    fn cell(&self, index: usize) -> IVec2 {
+        IVec2::new(index as i32 % self.width, index as i32 / self.width)
+    }
+
+    fn step(&self, index: usize, dir: IVec2) -> Option<usize> {
+        let cell = self.cell(index) + dir;
+        if cell.x < 0 || cell.y < 0 || cell.x >= self.width || cell.y >= self.height {
+            return None;
+        }
+        Some(self.index(cell))
+    }
+
+    fn reachable(&self, player: usize, crates: &[usize]) -> Vec<bool> {
+        let mut reachable = vec![false; self.wall.len()];
+        reachable[player] = true;
+        let mut queue = VecDeque::from([player]);
+        while let Some(c) = queue.pop_front() {
+            for dir in DIRECTIONS {
+                let Some(next) = self.step(c, dir) else {
+                    continue;
+                };
+                if !reachable[next] && !self.wall[next] && !crates.contains(&next) {
+                    reachable[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        reachable
+    }
+
+    fn normalized_player(&self, player: usize, crates: &[usize]) -> usize {
+        let reachable = self.reachable(player, crates);
+        reachable.iter().position(|&r| r).unwrap_or(player)
+    }
+
+    // Each push moves one crate one cell, so the distance of every crate to its nearest goal
+    // never overestimates the pushes left
+    fn heuristic(&self, crates: &[usize]) -> usize {
+        crates
+            .iter()
+            .map(|&c| {
+                let cell = self.cell(c);
+                self.goals
+                    .iter()
+                    .map(|&goal| {
+                        let distance = (goal - cell).abs();
+                        (distance.x + distance.y) as usize
+                    })
+                    .min()
+                    .unwrap_or(0)
+            })
+            .sum()
+    }
+
+    /// A crate that can't move along either axis is stuck for good, which only matters when it
+    /// or a crate it leans on is off a goal.
+    fn is_freeze_deadlock(&self, crate_cell: usize, crates: &[usize]) -> bool {
+        let mut frozen = Vec::new();
+        self.is_frozen(crate_cell, crates, &mut Vec::new(), &mut frozen)
+            && frozen.iter().any(|&c| !self.goal[c])
+    }
+
+    // Crates found frozen along the way go into `frozen`, but only once this crate is frozen
+    // too, since they may have only been frozen by treating it as a wall
+    fn is_frozen(
+        &self,
+        crate_cell: usize,
+        crates: &[usize],
+        checking: &mut Vec<usize>,
+        frozen: &mut Vec<usize>,
+    ) -> bool {
+        checking.push(crate_cell);
+        let mut found = Vec::new();
+        let blocked = |board: &Board, checking: &mut Vec<usize>, found: &mut Vec<usize>, axis| {
+            let sides = [board.step(crate_cell, axis), board.step(crate_cell, -axis)];
+            // Walls on either side, or dead squares on both
+            if sides.iter().any(|side| side.is_none_or(|s| board.wall[s])) {
+                return true;
+            }
+            if sides.iter().all(|side| side.is_none_or(|s| board.dead[s])) {
+                return true;
+            }
+            // Crates being checked further up count as walls, which stops the recursion
+            sides.iter().flatten().any(|&s| {
+                crates.contains(&s)
+                    && (checking.contains(&s) || board.is_frozen(s, crates, checking, found))
+            })
+        };
+
+        let result = blocked(self, checking, &mut found, IVec2::new(1, 0))
+            && blocked(self, checking, &mut found, IVec2::new(0, 1));
+        checking.pop();
+        if result {
+            frozen.extend(found);
+            frozen.push(crate_cell);
+        }
+        result
+    }
+
+    // Turns the chain of pushes into player steps, walking to each push along the shortest path
+    fn replay(&self, nodes: &[Node], goal: usize, player: usize) -> Solution {
+        let mut pushes = Vec::new();
+        let mut id = Some(goal);
+        while let Some(node) = id.map(|id| &nodes[id]) {
+            if let Some(push) = node.push {
+                pushes.push(push);
+            }
+            id = node.parent;
+        }
+        pushes.reverse();
+
+        let mut crates = nodes[0].crates.clone();
+        let mut player = player;
+        let mut moves = Vec::new();
+        for &(crate_cell, dir) in &pushes {
+            let behind = self.step(crate_cell, -dir).unwrap();
+            moves.extend(self.walk(player, behind, &crates));
+            moves.push(dir);
+
+            let i = crates.iter().position(|&c| c == crate_cell).unwrap();
+            crates[i] = self.step(crate_cell, dir).unwrap();
+            player = crate_cell;
+        }
+
+        Solution {
+            moves,
+            pushes: pushes.len(),
+        }
+    }
+
+    fn walk(&self, from: usize, to: usize, crates: &[usize]) -> Vec<IVec2> {
+        let mut came_from = vec![None; self.wall.len()];
+        let mut queue = VecDeque::from([from]);
+        while let Some(c) = queue.pop_front() {
+            if c == to {
+                break;
+            }
+            for dir in DIRECTIONS {
+                let Some(next) = self.step(c, dir) else {
+                    continue;
+                };
+                if next != from
+                    && came_from[next].is_none()
+                    && !self.wall[next]
+                    && !crates.contains(&next)
+                {
+                    came_from[next] = Some((c, dir));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut steps = Vec::new();
+        let mut c = to;
+        while let Some((prev, dir)) = came_from[c] {
+            steps.push(dir);
+            c = prev;
+        }
+        steps.reverse();
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(xsb: &str) -> (Board, Vec<usize>) {
+        let level = SokoLevel::from_xsb("test", xsb).unwrap();
+        let board = Board::new(&level);
+        let crates = level.crate_starts.iter().map(|&c| board.index(c)).collect();
+        (board, crates)
+    }
+
+    #[test]
+    fn frozen_crates_on_goals_are_fine() {
+        let (board, crates) = board("#####\n#**##\n#   #\n#@  #\n#####");
+        let a = board.index(IVec2::new(2, 1));
+        assert!(!board.is_freeze_deadlock(a, &crates));
+    }
+
+    #[test]
+    fn frozen_crate_off_goal_is_a_deadlock() {
+        let (board, crates) = board("#####\n#*$##\n#  .#\n#@  #\n#####");
+        let b = board.index(IVec2::new(2, 1));
+        assert!(board.is_freeze_deadlock(b, &crates));
+    }
+
+    // A and C sit on goals against the top wall. Checking A first looks at B, which freezes D
+    // while treating B as a wall, then finds B can still move up or down. D mustn't be left
+    // counted as frozen just because A turns out frozen through C.
+    #[test]
+    fn failed_branches_dont_freeze_crates() {
+        let (board, crates) = board("#######\n### # #\n#**$$ #\n# ..  #\n#@    #\n#######");
+        let a = board.index(IVec2::new(2, 2));
+        assert!(!board.is_freeze_deadlock(a, &crates));
+    }
+}
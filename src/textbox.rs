@@ -1,6 +1,5 @@
 use crate::timer::Timer;
 use notan::draw::*;
-use notan::prelude::*;
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -15,21 +14,17 @@ pub struct Conversation {
 }
 
 impl Conversation {
-    pub fn new(messages: Vec<Message>) -> Self {
+    pub fn new(messages: Vec<Message>, font: Font) -> Self {
         let mut x = Conversation {
             messages,
             current: 0,
-            textbox: Textbox::new(Message::Text("".to_string())),
+            textbox: Textbox::new(Message::Text("".to_string()), font),
         };
 
         x.textbox.message = x.current_message();
         x
     }
 
-    pub fn setup(&mut self, gfx: &mut Graphics) {
-        self.textbox.setup(gfx);
-    }
-
     pub fn update(&mut self, delta: f32) {
         self.textbox.update(delta);
     }
@@ -42,6 +37,10 @@ impl Conversation {
         self.textbox.set_message(self.current_message());
     }
 
+    pub fn on_last_message(&self) -> bool {
+        self.current + 1 >= self.messages.len()
+    }
+
     pub fn draw(&self, draw: &mut Draw) {
         self.textbox.draw(draw);
     }
@@ -55,16 +54,16 @@ pub struct Textbox {
     pub message: Message,
     pub cursor: usize,
     print_timer: Timer,
-    font: Option<Font>,
+    font: Font,
 }
 
 impl Textbox {
-    pub fn new(message: Message) -> Self {
+    pub fn new(message: Message, font: Font) -> Self {
         Textbox {
             message,
             cursor: 0,
             print_timer: Timer::new(0.03),
-            font: None,
+            font,
         }
     }
 
@@ -81,14 +80,6 @@ impl Textbox {
         }
     }
 
-    pub fn setup(&mut self, gfx: &mut Graphics) {
-        let font = gfx
-            .create_font(include_bytes!("assets/alagard.ttf"))
-            .unwrap();
-
-        self.font = Some(font);
-    }
-
     pub fn advance(&mut self) -> bool {
         match &self.message {
             Message::Text(text) => {
@@ -125,7 +116,7 @@ impl Textbox {
                 let visible_text: String = wrapped_text.chars().take(self.cursor).collect();
 
                 for line in visible_text.lines() {
-                    draw.text(&self.font.unwrap(), line)
+                    draw.text(&self.font, line)
                         .position(x, y)
                         .size(font_size)
                         .h_align_left()
@@ -134,11 +125,11 @@ impl Textbox {
                 }
             }
             Message::Choice(option1, option2) => {
-                draw.text(&self.font.unwrap(), option1)
+                draw.text(&self.font, option1)
                     .position(x, y)
                     .size(font_size);
                 y += 30.0; // Adjust this value to set the vertical spacing between options
-                draw.text(&self.font.unwrap(), option2)
+                draw.text(&self.font, option2)
                     .position(x, y)
                     .size(font_size);
             }
@@ -1,17 +1,277 @@
+use crate::pathfinding::PathCache;
+use notan::draw::*;
+use notan::math::{Mat2, Mat3, Vec2};
+use notan::prelude::*;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
 pub const TILE_SIZE: f32 = 16.0;
 
-#[derive(Clone, Copy, PartialEq)]
+// Tiled stores flip/rotation flags in the top bits of every GID
+const TILED_GID_MASK: u32 = 0x0FFF_FFFF;
+const TILED_FLIP_HORIZONTAL: u32 = 0x8000_0000;
+const TILED_FLIP_VERTICAL: u32 = 0x4000_0000;
+const TILED_FLIP_DIAGONAL: u32 = 0x2000_0000;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum TileType {
     Empty,
     Solid,
     SlopeUpRight,
     SlopeUpLeft,
+    /// Only collides from above, see `Player::jump` for dropping through
+    OneWay,
+}
+
+impl TileType {
+    pub fn from_name(name: &str) -> Option<TileType> {
+        match name {
+            "" | "empty" => Some(TileType::Empty),
+            "solid" => Some(TileType::Solid),
+            "slope_up_right" => Some(TileType::SlopeUpRight),
+            "slope_up_left" => Some(TileType::SlopeUpLeft),
+            "one_way" => Some(TileType::OneWay),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Tileset {
+    pub first_gid: u32,
+    pub image: String,
+    pub columns: u32,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub margin: f32,
+    pub spacing: f32,
+    texture: Option<Texture>,
+}
+
+impl Tileset {
+    /// Source rect of a tile inside the tileset image, `id` is local to this tileset.
+    pub fn tile_rect(&self, id: u32) -> (f32, f32, f32, f32) {
+        let column = (id % self.columns) as f32;
+        let row = (id / self.columns) as f32;
+        (
+            self.margin + column * (self.tile_width + self.spacing),
+            self.margin + row * (self.tile_height + self.spacing),
+            self.tile_width,
+            self.tile_height,
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LayerKind {
+    Background,
+    Collision,
+    Foreground,
+}
+
+#[derive(Clone)]
+pub struct TileLayer {
+    pub kind: LayerKind,
+    /// Visual tile of every cell as a raw Tiled GID, flip flags included. 0 is empty.
+    pub gids: Vec<Vec<u32>>,
+    pub visible: bool,
+    pub opacity: f32,
+    /// How fast the layer scrolls relative to the camera, 1.0 moves with the world.
+    pub parallax: Vec2,
+}
+
+#[derive(Clone, Debug)]
+pub enum TriggerEvent {
+    /// Starts a conversation, one message per line of the `conversation` property
+    Conversation(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ObjectKind {
+    /// Where an entity starts, looked up by the object's name
+    Spawn,
+    /// Fires its event when the player walks into it
+    Trigger(TriggerEvent),
+    /// Sokoban crate and goal cells, the cell is whichever tile the object sits in
+    Crate,
+    Goal,
+    /// Something to shoot at in top-down mode
+    Target,
+    /// Top-down enemy, its `behavior` property picks what it does
+    Enemy,
+    /// Any other object, left to gameplay code to interpret through its properties
+    Region,
+}
+
+#[derive(Clone, Debug)]
+pub enum ObjectShape {
+    Point,
+    Rect(Vec2),
+    /// Points are relative to the object's position
+    Polygon(Vec<Vec2>),
+}
+
+#[derive(Clone, Debug)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub kind: ObjectKind,
+    pub pos: Vec2,
+    pub shape: ObjectShape,
+    pub properties: HashMap<String, serde_json::Value>,
+}
+
+impl MapObject {
+    pub fn contains(&self, point: Vec2) -> bool {
+        let p = point - self.pos;
+        match &self.shape {
+            ObjectShape::Point => false,
+            ObjectShape::Rect(size) => p.x >= 0.0 && p.y >= 0.0 && p.x < size.x && p.y < size.y,
+            ObjectShape::Polygon(points) => {
+                // Even-odd rule: count the edges a ray going right from the point crosses
+                let mut inside = false;
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    if (a.y > p.y) != (b.y > p.y)
+                        && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y)
+                    {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+
+    pub fn bool_property(&self, name: &str) -> bool {
+        self.properties
+            .get(name)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    }
+
+    pub fn str_property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).and_then(|value| value.as_str())
+    }
 }
 
+#[derive(Clone)]
 pub struct TileMap {
-    pub tiles: Vec<Vec<TileType>>,
+    // Changed through `set_tile` so cached paths get cleared
+    pub(crate) tiles: Vec<Vec<TileType>>,
+    pub layers: Vec<TileLayer>,
+    pub tilesets: Vec<Tileset>,
+    pub objects: Vec<MapObject>,
     pub width: usize,
     pub height: usize,
+    pub tile_size: f32,
+    pub(crate) path_cache: PathCache,
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledMap {
+    width: usize,
+    height: usize,
+    #[serde(rename = "tilewidth")]
+    tile_width: u32,
+    #[serde(rename = "tileheight")]
+    tile_height: u32,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<TiledLayer>,
+    tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledLayer {
+    #[serde(rename = "type")]
+    layer_type: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    data: Vec<u32>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default = "default_one")]
+    opacity: f32,
+    #[serde(rename = "parallaxx", default = "default_one")]
+    parallax_x: f32,
+    #[serde(rename = "parallaxy", default = "default_one")]
+    parallax_y: f32,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+    #[serde(default)]
+    objects: Vec<TiledObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledObject {
+    id: u32,
+    #[serde(default)]
+    name: String,
+    // Tiled 1.9 exports an object's `type` as `class`
+    #[serde(rename = "type", alias = "class", default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<TiledPoint>>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledPoint {
+    x: f32,
+    y: f32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledTileset {
+    #[serde(rename = "firstgid")]
+    first_gid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    image: String,
+    #[serde(default)]
+    columns: u32,
+    #[serde(rename = "tilewidth", default)]
+    tile_width: u32,
+    #[serde(rename = "tileheight", default)]
+    tile_height: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    tiles: Vec<TiledTile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TiledProperty {
+    name: String,
+    value: serde_json::Value,
 }
 
 impl TileMap {
@@ -19,20 +279,236 @@ impl TileMap {
         let tiles = vec![vec![TileType::Empty; width]; height];
         TileMap {
             tiles,
+            layers: Vec::new(),
+            tilesets: Vec::new(),
+            objects: Vec::new(),
             width,
             height,
+            tile_size: TILE_SIZE,
+            path_cache: PathCache::default(),
         }
     }
 
+    /// Builds a map from a Tiled JSON export. Each tile's collision comes from its
+    /// `collision` custom property in the tileset, tiles without one are empty.
+    ///
+    /// Collision is read from a single tile layer: the one with a `collision` bool property,
+    /// else the one named "collision", else the first. Layers below it are drawn as background
+    /// and layers above it as foreground.
+    ///
+    /// Objects from object layers are read by their class: `spawn` marks a named spawn point
+    /// and `trigger` a region with an event, anything else is kept as a plain region.
+    pub fn from_tiled(json: &str) -> Result<Self, String> {
+        let map: TiledMap = serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        if map.infinite {
+            return Err("infinite Tiled maps are not supported".to_string());
+        }
+        if map.tile_width != map.tile_height {
+            return Err(format!(
+                "tiles must be square, got {}x{}",
+                map.tile_width, map.tile_height
+            ));
+        }
+
+        let mut collisions = HashMap::new();
+        for tileset in &map.tilesets {
+            if let Some(source) = &tileset.source {
+                return Err(format!("external tileset '{}' is not supported", source));
+            }
+            for tile in &tileset.tiles {
+                let Some(property) = tile.properties.iter().find(|p| p.name == "collision") else {
+                    continue;
+                };
+                let name = property.value.as_str().unwrap_or_default();
+                let tile_type = TileType::from_name(name)
+                    .ok_or_else(|| format!("unknown collision type '{}'", name))?;
+                collisions.insert(tileset.first_gid + tile.id, tile_type);
+            }
+        }
+
+        let mut tilemap = TileMap::new(map.width, map.height);
+        tilemap.tile_size = map.tile_width as f32;
+        tilemap.tilesets = map
+            .tilesets
+            .iter()
+            .map(|tileset| Tileset {
+                first_gid: tileset.first_gid,
+                image: tileset.image.clone(),
+                columns: tileset.columns.max(1),
+                tile_width: tileset.tile_width as f32,
+                tile_height: tileset.tile_height as f32,
+                margin: tileset.margin as f32,
+                spacing: tileset.spacing as f32,
+                texture: None,
+            })
+            .collect();
+
+        let tile_layers: Vec<_> = map
+            .layers
+            .iter()
+            .filter(|l| l.layer_type == "tilelayer")
+            .collect();
+        let collision_layer = tile_layers
+            .iter()
+            .position(|l| {
+                l.properties
+                    .iter()
+                    .any(|p| p.name == "collision" && p.value.as_bool() == Some(true))
+            })
+            .or_else(|| tile_layers.iter().position(|l| l.name == "collision"))
+            .unwrap_or(0);
+
+        for (index, layer) in tile_layers.iter().enumerate() {
+            let kind = match index.cmp(&collision_layer) {
+                Ordering::Less => LayerKind::Background,
+                Ordering::Equal => LayerKind::Collision,
+                Ordering::Greater => LayerKind::Foreground,
+            };
+            let mut gids = vec![vec![0; map.width]; map.height];
+
+            for (i, &gid) in layer.data.iter().enumerate() {
+                let (x, y) = (i % map.width, i / map.width);
+                if gid & TILED_GID_MASK == 0 || y >= map.height {
+                    continue;
+                }
+                gids[y][x] = gid;
+                if kind != LayerKind::Collision {
+                    continue;
+                }
+                if let Some(&tile_type) = collisions.get(&(gid & TILED_GID_MASK)) {
+                    tilemap.set_tile(x, y, tile_type);
+                }
+            }
+
+            tilemap.layers.push(TileLayer {
+                kind,
+                gids,
+                visible: layer.visible,
+                opacity: layer.opacity,
+                parallax: Vec2::new(layer.parallax_x, layer.parallax_y),
+            });
+        }
+
+        for layer in map.layers.iter().filter(|l| l.layer_type == "objectgroup") {
+            for object in &layer.objects {
+                tilemap.objects.push(map_object(object)?);
+            }
+        }
+
+        Ok(tilemap)
+    }
+
+    pub fn spawn_point(&self, name: &str) -> Option<Vec2> {
+        self.objects
+            .iter()
+            .find(|o| matches!(o.kind, ObjectKind::Spawn) && o.name == name)
+            .map(|o| o.pos)
+    }
+
+    pub fn setup(&mut self, gfx: &mut Graphics) {
+        for tileset in &mut self.tilesets {
+            let Some(bytes) = tileset_image(&tileset.image) else {
+                continue;
+            };
+            let texture = gfx
+                .create_texture()
+                .from_image(bytes)
+                .with_filter(TextureFilter::Nearest, TextureFilter::Nearest)
+                .build()
+                .unwrap();
+            tileset.texture = Some(texture);
+        }
+    }
+
+    /// Draws the background and collision layers, call before drawing entities.
+    /// `camera` is the translation applied to the world, it drives parallax.
+    pub fn draw_background(&self, draw: &mut Draw, camera: Vec2) {
+        for layer in self
+            .layers
+            .iter()
+            .filter(|l| l.kind != LayerKind::Foreground)
+        {
+            self.draw_layer(draw, layer, camera);
+        }
+    }
+
+    /// Draws the foreground layers, call after drawing entities.
+    pub fn draw_foreground(&self, draw: &mut Draw, camera: Vec2) {
+        for layer in self
+            .layers
+            .iter()
+            .filter(|l| l.kind == LayerKind::Foreground)
+        {
+            self.draw_layer(draw, layer, camera);
+        }
+    }
+
+    fn draw_layer(&self, draw: &mut Draw, layer: &TileLayer, camera: Vec2) {
+        if !layer.visible || layer.opacity <= 0.0 {
+            return;
+        }
+
+        // A parallax of 0 keeps the layer fixed on screen, 1 scrolls it with the world
+        let offset = -camera * (Vec2::ONE - layer.parallax);
+
+        for (y, row) in layer.gids.iter().enumerate() {
+            for (x, &gid) in row.iter().enumerate() {
+                if gid & TILED_GID_MASK != 0 {
+                    let pos = Vec2::new(x as f32, y as f32) * self.tile_size + offset;
+                    self.draw_tile(draw, gid, pos, layer.opacity);
+                }
+            }
+        }
+    }
+
+    fn draw_tile(&self, draw: &mut Draw, gid: u32, pos: Vec2, opacity: f32) {
+        let id = gid & TILED_GID_MASK;
+        let Some(tileset) = self.tilesets.iter().rev().find(|t| t.first_gid <= id) else {
+            return;
+        };
+        let Some(texture) = &tileset.texture else {
+            return;
+        };
+
+        let (sx, sy, sw, sh) = tileset.tile_rect(id - tileset.first_gid);
+        // Tiles taller than the grid are anchored to the bottom of their cell, like Tiled does
+        let pos = Vec2::new(pos.x, pos.y + self.tile_size - sh);
+        let center = pos + Vec2::new(sw, sh) / 2.0;
+
+        // Diagonal flip is applied first, then horizontal and vertical
+        let mut flip = Mat2::IDENTITY;
+        if gid & TILED_FLIP_DIAGONAL != 0 {
+            flip = Mat2::from_cols_array(&[0.0, 1.0, 1.0, 0.0]) * flip;
+        }
+        if gid & TILED_FLIP_HORIZONTAL != 0 {
+            flip = Mat2::from_diagonal(Vec2::new(-1.0, 1.0)) * flip;
+        }
+        if gid & TILED_FLIP_VERTICAL != 0 {
+            flip = Mat2::from_diagonal(Vec2::new(1.0, -1.0)) * flip;
+        }
+
+        draw.image(texture)
+            .crop((sx, sy), (sw, sh))
+            .position(pos.x, pos.y)
+            .alpha(opacity)
+            .transform(
+                Mat3::from_translation(center)
+                    * Mat3::from_mat2(flip)
+                    * Mat3::from_translation(-center),
+            );
+    }
+
     pub fn set_tile(&mut self, x: usize, y: usize, tile_type: TileType) {
         if x < self.width && y < self.height {
             self.tiles[y][x] = tile_type;
+            self.path_cache.clear();
         }
     }
 
     pub fn get_tile_type(&self, x: f32, y: f32) -> TileType {
-        let tile_x = (x / TILE_SIZE) as usize;
-        let tile_y = (y / TILE_SIZE) as usize;
+        let tile_x = (x / self.tile_size) as usize;
+        let tile_y = (y / self.tile_size) as usize;
         if tile_x < self.width && tile_y < self.height {
             self.tiles[tile_y][tile_x]
         } else {
@@ -42,20 +518,20 @@ impl TileMap {
 
     pub fn is_pixel_solid(&self, x: f32, y: f32) -> bool {
         let tile_type = self.get_tile_type(x, y);
-        let px = (x % TILE_SIZE) as u32;
-        let py = (y % TILE_SIZE) as u32;
+        let px = (x % self.tile_size) as u32;
+        let py = (y % self.tile_size) as u32;
 
         match tile_type {
             TileType::Solid => true,
-            TileType::SlopeUpRight => px >= TILE_SIZE as u32 - py,
+            TileType::SlopeUpRight => px >= self.tile_size as u32 - py,
             TileType::SlopeUpLeft => px < py,
-            TileType::Empty => false,
+            TileType::OneWay | TileType::Empty => false,
         }
     }
 
     pub fn is_tile_type(&self, x: f32, y: f32, tile_type: TileType) -> bool {
-        let tile_x = (x / TILE_SIZE) as usize;
-        let tile_y = (y / TILE_SIZE) as usize;
+        let tile_x = (x / self.tile_size) as usize;
+        let tile_y = (y / self.tile_size) as usize;
         if tile_x < self.width && tile_y < self.height {
             self.tiles[tile_y][tile_x] == tile_type
         } else {
@@ -63,3 +539,198 @@ impl TileMap {
         }
     }
 }
+
+fn map_object(object: &TiledObject) -> Result<MapObject, String> {
+    let properties: HashMap<_, _> = object
+        .properties
+        .iter()
+        .map(|p| (p.name.clone(), p.value.clone()))
+        .collect();
+
+    let kind = match object.class.as_str() {
+        "spawn" => ObjectKind::Spawn,
+        "trigger" => {
+            let Some(conversation) = properties.get("conversation").and_then(|v| v.as_str()) else {
+                return Err(format!("trigger '{}' has no event", object.name));
+            };
+            // Blank lines would show up as empty textboxes
+            let lines: Vec<String> = conversation
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.to_string())
+                .collect();
+            if lines.is_empty() {
+                return Err(format!(
+                    "trigger '{}' has an empty conversation",
+                    object.name
+                ));
+            }
+            ObjectKind::Trigger(TriggerEvent::Conversation(lines))
+        }
+        "crate" => ObjectKind::Crate,
+        "goal" => ObjectKind::Goal,
+        "target" => ObjectKind::Target,
+        "enemy" => ObjectKind::Enemy,
+        _ => ObjectKind::Region,
+    };
+
+    let shape = if let Some(polygon) = &object.polygon {
+        ObjectShape::Polygon(polygon.iter().map(|p| Vec2::new(p.x, p.y)).collect())
+    } else if object.point || (object.width == 0.0 && object.height == 0.0) {
+        ObjectShape::Point
+    } else {
+        // Ellipses are treated as their bounding box
+        ObjectShape::Rect(Vec2::new(object.width, object.height))
+    };
+
+    Ok(MapObject {
+        id: object.id,
+        name: object.name.clone(),
+        kind,
+        pos: Vec2::new(object.x, object.y),
+        shape,
+        properties,
+    })
+}
+
+// Tileset images are embedded so maps also load on the web build
+fn tileset_image(name: &str) -> Option<&'static [u8]> {
+    match name {
+        "tileset.png" => Some(include_bytes!("../assets/tileset.png")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(conversation: &str) -> Result<MapObject, String> {
+        let object: TiledObject = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "sign",
+            "type": "trigger",
+            "x": 0.0,
+            "y": 0.0,
+            "properties": [
+                { "name": "conversation", "type": "string", "value": conversation }
+            ]
+        }))
+        .unwrap();
+        map_object(&object)
+    }
+
+    fn layer(name: &str, data: [u32; 6]) -> serde_json::Value {
+        serde_json::json!({ "type": "tilelayer", "name": name, "data": data })
+    }
+
+    // A 3x2 map: a parallax background, the collision layer, decoration above it and one
+    // spawn point. Tiles 0, 1 and 4 of the tileset are solid, a slope and one-way.
+    fn tiled(collision: &str) -> serde_json::Value {
+        let mut background = layer("sky", [2, 2, 2, 0, 0, 0]);
+        background["parallaxx"] = 0.5.into();
+        serde_json::json!({
+            "width": 3,
+            "height": 2,
+            "tilewidth": 16,
+            "tileheight": 16,
+            "tilesets": [{
+                "firstgid": 1,
+                "image": "tiles.png",
+                "columns": 4,
+                "tilewidth": 16,
+                "tileheight": 16,
+                "tiles": [
+                    { "id": 0, "properties": [{ "name": "collision", "value": collision }] },
+                    {
+                        "id": 1,
+                        "properties": [{ "name": "collision", "value": "slope_up_right" }]
+                    },
+                    { "id": 4, "properties": [{ "name": "collision", "value": "one_way" }] }
+                ]
+            }],
+            "layers": [
+                background,
+                layer(
+                    "collision",
+                    [1, TILED_FLIP_HORIZONTAL | 5, 2, 0, 0, TILED_FLIP_VERTICAL | 1],
+                ),
+                layer("decor", [0, 0, 0, 1, 0, 0]),
+                {
+                    "type": "objectgroup",
+                    "objects": [
+                        {
+                            "id": 1,
+                            "name": "player",
+                            "type": "spawn",
+                            "x": 8.0,
+                            "y": 24.0,
+                            "point": true
+                        }
+                    ]
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn collision_comes_from_tile_properties() {
+        let map = TileMap::from_tiled(&tiled("solid").to_string()).unwrap();
+        assert_eq!(
+            map.tiles,
+            [
+                [TileType::Solid, TileType::OneWay, TileType::SlopeUpRight],
+                // Decoration tiles above the collision layer don't collide
+                [TileType::Empty, TileType::Empty, TileType::Solid],
+            ]
+        );
+
+        let err = TileMap::from_tiled(&tiled("lava").to_string()).err();
+        assert_eq!(err.as_deref(), Some("unknown collision type 'lava'"));
+    }
+
+    #[test]
+    fn flip_bits_are_kept_for_drawing_only() {
+        let map = TileMap::from_tiled(&tiled("solid").to_string()).unwrap();
+        let gid = map.layers[1].gids[0][1];
+        assert_eq!(gid & TILED_GID_MASK, 5);
+        assert_eq!(gid & TILED_FLIP_HORIZONTAL, TILED_FLIP_HORIZONTAL);
+        assert_eq!(map.layers[1].gids[1][2], TILED_FLIP_VERTICAL | 1);
+        assert_eq!(map.tiles[0][1], TileType::OneWay);
+    }
+
+    #[test]
+    fn layers_and_objects_land_in_their_fields() {
+        let map = TileMap::from_tiled(&tiled("solid").to_string()).unwrap();
+        let kinds: Vec<LayerKind> = map.layers.iter().map(|layer| layer.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                LayerKind::Background,
+                LayerKind::Collision,
+                LayerKind::Foreground
+            ]
+        );
+        assert_eq!(map.layers[0].parallax, Vec2::new(0.5, 1.0));
+        assert_eq!(map.layers[2].gids[1][0], 1);
+
+        assert_eq!(map.objects.len(), 1);
+        assert_eq!(map.spawn_point("player"), Some(Vec2::new(8.0, 24.0)));
+        assert_eq!(map.tile_size, 16.0);
+    }
+
+    #[test]
+    fn conversation_lines_skip_blanks() {
+        let object = trigger("Hello\n\n  \nBye").unwrap();
+        let ObjectKind::Trigger(TriggerEvent::Conversation(lines)) = object.kind else {
+            panic!("expected a conversation trigger");
+        };
+        assert_eq!(lines, ["Hello", "Bye"]);
+    }
+
+    #[test]
+    fn empty_conversations_are_rejected() {
+        assert!(trigger("").is_err());
+        assert!(trigger("  \n\t").is_err());
+    }
+}
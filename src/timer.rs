@@ -23,6 +23,10 @@ impl Timer {
         self.time = 0.0;
     }
 
+    pub fn finish(&mut self) {
+        self.time = self.duration;
+    }
+
     pub fn progress(&self) -> f32 {
         (self.time / self.duration).clamp(0.0, 1.0)
     }
@@ -0,0 +1,105 @@
+/// Simulation rate, every tick advances the game by exactly this much
+pub const FIXED_DT: f32 = 1.0 / 60.0;
+
+// Caps the ticks run in one frame so a long stall doesn't snowball into ever longer frames
+const MAX_TICKS_PER_FRAME: u32 = 5;
+
+/// Accumulates variable frame time and turns it into a whole number of fixed ticks.
+pub struct FixedTimestep {
+    pub step: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        FixedTimestep {
+            step,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds a frame's delta time and returns how many ticks to simulate.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.step && ticks < MAX_TICKS_PER_FRAME {
+            self.accumulator -= self.step;
+            ticks += 1;
+        }
+
+        if ticks == MAX_TICKS_PER_FRAME {
+            self.accumulator = self.accumulator.min(self.step);
+        }
+        ticks
+    }
+
+    /// How far rendering is between the previous and the current tick, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ticks run over `seconds` of frames `frame_time` long
+    fn ticks_over(frame_time: f32, seconds: f32) -> u32 {
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        let frames = (seconds / frame_time).round() as u32;
+        (0..frames).map(|_| timestep.advance(frame_time)).sum()
+    }
+
+    #[test]
+    fn same_tick_rate_at_any_refresh_rate() {
+        for refresh_rate in [30.0, 60.0, 144.0] {
+            let ticks = ticks_over(1.0 / refresh_rate, 10.0);
+            // Float rounding can leave the last tick a hair short of due
+            assert!(
+                (599..=600).contains(&ticks),
+                "{} Hz ran {} ticks",
+                refresh_rate,
+                ticks
+            );
+        }
+    }
+
+    #[test]
+    fn ticks_per_frame() {
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        assert_eq!(timestep.advance(1.0 / 30.0), 2);
+
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        assert_eq!(timestep.advance(FIXED_DT), 1);
+
+        // A 144 Hz frame is too short for a tick on its own, the third one adds up to one
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        let frame = 1.0 / 144.0;
+        assert_eq!(timestep.advance(frame), 0);
+        assert_eq!(timestep.advance(frame), 0);
+        assert_eq!(timestep.advance(frame), 1);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut timestep = FixedTimestep::new(FIXED_DT);
+        assert_eq!(timestep.advance(1.0), MAX_TICKS_PER_FRAME);
+        // Only up to one tick's worth is carried over, not the rest of the stall
+        assert_eq!(timestep.advance(0.0), 1);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+
+    #[test]
+    fn alpha_is_the_fraction_of_a_tick_left_over() {
+        let mut timestep = FixedTimestep::new(0.25);
+        assert_eq!(timestep.alpha(), 0.0);
+        timestep.advance(0.125);
+        assert_eq!(timestep.alpha(), 0.5);
+        timestep.advance(0.1875);
+        assert_eq!(timestep.alpha(), 0.25);
+
+        timestep.advance(10.0);
+        assert_eq!(timestep.alpha(), 1.0);
+    }
+}
@@ -1,51 +1,70 @@
-use crate::tilemap::{TileMap, TileType, TILE_SIZE};
+use crate::aseprite::{Animation, AnimationEvent, SpriteSheet};
+use crate::combat::{Damage, Health, Hurtbox, Team, PLAYER_OWNER};
+use crate::input::{Action, ActionSnapshot, Axis};
+use crate::tilemap::{TileMap, TileType};
 use notan::draw::*;
-use notan::math::{Mat3, Rect, Vec2};
+use notan::math::Vec2;
 use notan::prelude::*;
 
 pub struct TopDownPlayer {
     pub pos: Vec2,
+    pub prev_pos: Vec2,
     pub velocity: Vec2,
     pub temp_velocity: Vec2,
     pub size: Vec2,
-    pub shape: Rect,
-    pub render_matrix: Mat3,
     pub acceleration: Vec2,
     pub friction: Vec2,
     pub max_speed: Vec2,
     pub moved_amount: Vec2,
+    pub health: Health,
+    pub sprite: Animation,
+    pub facing_left: bool,
     collision_types: Vec<TileType>,
     sprint_speed_multiplier: f32,
 }
 
 impl TopDownPlayer {
-    pub fn new(x: f32, y: f32) -> Self {
-        let mut shape = Rectangle::new((0.0, 0.0), (8.0, 8.0));
-        shape.translate(0., 4.);
-
+    pub fn new(x: f32, y: f32, sheet: &SpriteSheet) -> Self {
         TopDownPlayer {
             pos: Vec2::new(x, y),
+            prev_pos: Vec2::new(x, y),
             velocity: Vec2::ZERO,
             temp_velocity: Vec2::ZERO,
             size: Vec2::new(8.0, 8.0),
-            shape: Rect {
-                x: 0.0,
-                y: 0.0,
-                width: 8.0,
-                height: 16.0,
-            },
-            render_matrix: Mat3::from_translation(Vec2::new(0., -8.)),
             acceleration: Vec2::new(420.0, 420.0),
             friction: Vec2::new(1.15, 1.15),
             max_speed: Vec2::new(2., 2.),
             moved_amount: Vec2::ZERO,
+            health: Health::new(5.0, 1.0),
+            sprite: Animation::new(sheet),
+            facing_left: false,
             collision_types: vec![TileType::Solid],
             sprint_speed_multiplier: 2.,
         }
     }
 
     pub fn update(&mut self, tilemap: &TileMap, dt: f32) {
+        self.prev_pos = self.pos;
         self.velocity /= self.friction;
+        self.health.update(dt);
+        if self.velocity.x.abs() > 0.1 {
+            self.facing_left = self.velocity.x < 0.0;
+        }
+
+        // Sprinting plays the walk faster
+        let moving = self.velocity.length() > 0.1;
+        self.sprite.speed = 1.0 + self.velocity.length() / self.max_speed.length();
+        let mut flinching = self.sprite.tag() == "hurt";
+        for event in self.sprite.update(dt) {
+            let AnimationEvent::Finished(tag) = event;
+            if tag == "hurt" {
+                flinching = false;
+            }
+        }
+        // Sheets without these tags keep playing whatever they were
+        if !flinching {
+            let _ = self.sprite.play(if moving { "walk" } else { "idle" });
+        }
 
         self.move_object(tilemap, dt);
         self.clamp_speed();
@@ -92,20 +111,7 @@ impl TopDownPlayer {
     }
 
     fn collide(&self, tilemap: &TileMap, x: f32, y: f32) -> bool {
-        let left = x;
-        let right = x + self.size.x - 1.0;
-        let top = y;
-        let bottom = y + self.size.y - 1.0;
-
-        for check_y in [top, bottom] {
-            for check_x in [left, right] {
-                let tile_type = tilemap.get_tile_type(check_x, check_y);
-                if tile_type == TileType::Solid {
-                    return true;
-                }
-            }
-        }
-        false
+        collides(tilemap, Vec2::new(x, y), self.size)
     }
 
     fn hit_wall(&mut self) {
@@ -119,34 +125,13 @@ impl TopDownPlayer {
         self.velocity.y = self.velocity.y.clamp(-max_speed.y, max_speed.y);
     }
 
-    pub fn move_direction(
-        &mut self,
-        up: bool,
-        down: bool,
-        left: bool,
-        right: bool,
-        sprint: bool,
-        dt: f32,
-    ) {
-        let mut direction = Vec2::ZERO;
-        if up {
-            direction.y -= 1.0;
-        }
-        if down {
-            direction.y += 1.0;
-        }
-        if left {
-            direction.x -= 1.0;
-        }
-        if right {
-            direction.x += 1.0;
-        }
-
-        if direction != Vec2::ZERO {
+    pub fn move_direction(&mut self, input: &ActionSnapshot, dt: f32) {
+        let mut direction = Vec2::new(input.axis(Axis::MoveX), input.axis(Axis::MoveY));
+        if direction.length() > 1.0 {
             direction = direction.normalize();
         }
 
-        let acceleration = if sprint {
+        let acceleration = if input.held(Action::Sprint) {
             self.acceleration * self.sprint_speed_multiplier
         } else {
             self.acceleration
@@ -155,19 +140,75 @@ impl TopDownPlayer {
         self.velocity += direction * acceleration * dt;
     }
 
-    pub fn draw(&self, draw: &mut Draw) {
-        let pos = self.pos;
+    /// Takes the hit unless still invulnerable from the last one, returns whether it landed.
+    pub fn hurt(&mut self, damage: &Damage) -> bool {
+        if !self.health.take(damage) {
+            return false;
+        }
+        self.velocity += damage.knockback;
+        let _ = self.sprite.play_once("hurt");
+        true
+    }
 
-        draw.transform().push(self.render_matrix);
+    /// Fitted to the sprite's `hurtbox` slice, or the collision box without one.
+    pub fn hurtbox(&self) -> Hurtbox {
+        let slice = self
+            .sprite
+            .slice_bounds("hurtbox", self.feet(1.0), self.facing_left);
+        match slice {
+            Some(bounds) => Hurtbox {
+                position: Vec2::new(
+                    bounds.x + bounds.width / 2.0,
+                    bounds.y + bounds.height / 2.0,
+                ),
+                radius: bounds.width.min(bounds.height) / 2.0,
+                team: Team::Player,
+                owner: PLAYER_OWNER,
+            },
+            None => Hurtbox {
+                position: self.pos + self.size / 2.0,
+                radius: self.size.x / 2.0,
+                team: Team::Player,
+                owner: PLAYER_OWNER,
+            },
+        }
+    }
+
+    /// Where shots leave from, the sprite's `muzzle` slice or the middle of the player.
+    pub fn muzzle(&self, alpha: f32) -> Vec2 {
+        self.sprite
+            .slice_pivot("muzzle", self.feet(alpha), self.facing_left)
+            .unwrap_or(self.render_pos(alpha) + self.size / 2.0)
+    }
+
+    // Bottom middle of the collision box, where the sprite's origin goes
+    fn feet(&self, alpha: f32) -> Vec2 {
+        self.render_pos(alpha) + Vec2::new(self.size.x / 2.0, self.size.y)
+    }
+
+    /// Position to draw at, `alpha` blends from the previous tick to the current one.
+    pub fn render_pos(&self, alpha: f32) -> Vec2 {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
 
-        draw.rect((0., 0.), (self.shape.width, self.shape.height))
-            .color(Color::WHITE)
-            .translate(pos.x + self.shape.x, pos.y + self.shape.y);
+    pub fn draw(&self, draw: &mut Draw, alpha: f32) {
+        // Blink while invulnerable
+        let opacity = if self.health.is_invulnerable()
+            && (self.health.iframe_time() * 10.0) as i32 % 2 == 0
+        {
+            0.3
+        } else {
+            1.0
+        };
 
-        draw.transform().pop();
+        self.sprite
+            .draw(draw, self.feet(alpha), self.facing_left, opacity);
     }
 
     pub fn render_debug(&self, draw: &mut Draw, tilemap: &TileMap) {
+        self.sprite
+            .draw_slices(draw, self.feet(1.0), self.facing_left);
+
         // Render player bounding box
         draw.rect((self.pos.x, self.pos.y), (self.size.x, self.size.y))
             .stroke_color(Color::RED);
@@ -202,13 +243,14 @@ impl TopDownPlayer {
         .color(Color::RED);
 
         // Render tiles that the player is colliding with
+        let tile_size = tilemap.tile_size;
         for y in (self.pos.y as i32 - 1..=(self.pos.y + self.size.y) as i32 + 1) {
             for x in (self.pos.x as i32 - 1..=(self.pos.x + self.size.x) as i32 + 1) {
                 let tile_type = tilemap.get_tile_type(x as f32, y as f32);
                 if tile_type == TileType::Solid {
                     draw.rect(
-                        (x as f32 * TILE_SIZE, y as f32 * TILE_SIZE),
-                        (TILE_SIZE, TILE_SIZE),
+                        (x as f32 * tile_size, y as f32 * tile_size),
+                        (tile_size, tile_size),
                     )
                     .stroke_color(Color::BLUE);
                 }
@@ -216,3 +258,21 @@ impl TopDownPlayer {
         }
     }
 }
+
+/// Whether a box at `pos` overlaps a solid tile, checked at its corners.
+pub fn collides(tilemap: &TileMap, pos: Vec2, size: Vec2) -> bool {
+    let left = pos.x;
+    let right = pos.x + size.x - 1.0;
+    let top = pos.y;
+    let bottom = pos.y + size.y - 1.0;
+
+    for check_y in [top, bottom] {
+        for check_x in [left, right] {
+            let tile_type = tilemap.get_tile_type(check_x, check_y);
+            if tile_type == TileType::Solid {
+                return true;
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,44 @@
+use crate::tilemap::{ObjectKind, TileMap, TriggerEvent};
+use notan::math::Vec2;
+use std::collections::HashSet;
+
+/// Tracks which triggers an entity is standing in, so each one fires when it is entered
+/// rather than every frame. Triggers with a `once` property only ever fire a single time.
+pub struct TriggerTracker {
+    inside: HashSet<u32>,
+    fired: HashSet<u32>,
+}
+
+impl TriggerTracker {
+    pub fn new() -> Self {
+        TriggerTracker {
+            inside: HashSet::new(),
+            fired: HashSet::new(),
+        }
+    }
+
+    pub fn update(&mut self, tilemap: &TileMap, pos: Vec2) -> Vec<TriggerEvent> {
+        let mut events = Vec::new();
+        let mut inside = HashSet::new();
+
+        for object in &tilemap.objects {
+            let ObjectKind::Trigger(event) = &object.kind else {
+                continue;
+            };
+            if !object.contains(pos) {
+                continue;
+            }
+
+            inside.insert(object.id);
+            let entered = !self.inside.contains(&object.id);
+            let spent = object.bool_property("once") && self.fired.contains(&object.id);
+            if entered && !spent {
+                self.fired.insert(object.id);
+                events.push(event.clone());
+            }
+        }
+
+        self.inside = inside;
+        events
+    }
+}
@@ -0,0 +1,51 @@
+use crate::level_pack::LevelPack;
+
+/// Solves every level of each pack and prints the best push count. Checks the built-in
+/// starter pack when no paths are given. Fails if any level has no solution.
+pub fn run(paths: &[String]) -> Result<(), String> {
+    let packs = if paths.is_empty() {
+        vec![(
+            "starter.xsb".to_string(),
+            include_str!("../assets/soko/starter.xsb").to_string(),
+        )]
+    } else {
+        paths
+            .iter()
+            .map(|path| {
+                let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                Ok((path.clone(), text))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
+
+    let mut failed = 0;
+    for (name, text) in packs {
+        let pack = LevelPack::parse(&text).map_err(|e| format!("{}: {}", name, e))?;
+        println!("{}", name);
+
+        for level in &pack.levels {
+            match crate::solver::solve(
+                level,
+                level.player_start,
+                &level.crate_starts,
+                crate::solver::VALIDATE_SEARCH_LIMIT,
+            ) {
+                Ok(solution) => println!(
+                    "  {}: {} pushes, {} moves",
+                    level.title,
+                    solution.pushes,
+                    solution.moves.len()
+                ),
+                Err(e) => {
+                    failed += 1;
+                    println!("  {}: {}", level.title, e);
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} level(s) could not be solved", failed));
+    }
+    Ok(())
+}